@@ -0,0 +1,233 @@
+//! Utilities for comparing compression algorithms and IO strategies on your own data.
+//!
+//! Compression ratios and throughput depend heavily on the values being stored,
+//! so [compare_compressors] runs every enabled algorithm over a sample of real
+//! values to help pick one empirically. [compare_read_preallocation] and
+//! [compare_get_many] do the same for reading entries.
+
+use std::{
+    borrow::Cow,
+    hash::Hash,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use futures_util::future::join_all;
+
+use crate::{Cache, CacheKey, CacheStrategy, CompressionStrategy, DiskUtil, Noop, Result};
+
+/// The results of running a single compression algorithm over a set of samples.
+#[derive(Debug, Clone)]
+pub struct CompressorReport {
+    /// The name of the compression algorithm.
+    pub algorithm: &'static str,
+    /// The total size of the samples before compression.
+    pub original_bytes: usize,
+    /// The total size of the samples after compression.
+    pub compressed_bytes: usize,
+    /// The total time spent compressing the samples.
+    pub compress_time: Duration,
+    /// The total time spent decompressing the samples.
+    pub decompress_time: Duration,
+}
+
+impl CompressorReport {
+    /// The compressed size relative to the original size.
+    ///
+    /// Lower is better. Returns `1.0` if the samples were empty.
+    pub fn ratio(&self) -> f64 {
+        if self.original_bytes == 0 {
+            return 1.0;
+        }
+        self.compressed_bytes as f64 / self.original_bytes as f64
+    }
+}
+
+/// Compress and decompress `samples` with every enabled compression algorithm,
+/// using its default level.
+///
+/// Returns one report per algorithm, starting with an uncompressed `"none"` baseline.
+/// Enable the `comp_*` features for the algorithms you want to compare.
+pub async fn compare_compressors(samples: &[Vec<u8>]) -> Result<Vec<CompressorReport>> {
+    #[allow(unused_mut)]
+    let mut reports = vec![measure("none", &Noop, samples).await?];
+    #[cfg(feature = "comp_zstd")]
+    reports.push(measure("zstd", &crate::compression::Zstd::default(), samples).await?);
+    #[cfg(feature = "comp_gzip")]
+    reports.push(measure("gzip", &crate::compression::Gzip::default(), samples).await?);
+    #[cfg(feature = "comp_brotli")]
+    reports.push(measure("brotli", &crate::compression::Brotli::default(), samples).await?);
+    Ok(reports)
+}
+
+/// The results of reading a set of files, with and without pre-allocating the read buffer.
+#[derive(Debug, Clone)]
+pub struct ReadReport {
+    /// The number of files read.
+    pub files: usize,
+    /// The total size of the files.
+    pub bytes: usize,
+    /// The total time spent reading with a buffer sized from each file's metadata.
+    pub preallocated_time: Duration,
+    /// The total time spent reading with a buffer that grows while reading.
+    pub growing_time: Duration,
+}
+
+/// Read every file in `paths` the way recovery does, with a buffer sized from the
+/// file's metadata, and again with a buffer that starts out empty and grows.
+///
+/// Each pass reads every file once, so run it on files that are already in the
+/// page cache to compare allocation rather than disk speed.
+pub async fn compare_read_preallocation(paths: &[PathBuf]) -> Result<ReadReport> {
+    let mut report = ReadReport {
+        files: paths.len(),
+        bytes: 0,
+        preallocated_time: Duration::ZERO,
+        growing_time: Duration::ZERO,
+    };
+
+    let start = Instant::now();
+    for path in paths {
+        let (_, data) = DiskUtil::read(path, None).await?;
+        report.bytes += data.len();
+    }
+    report.preallocated_time = start.elapsed();
+
+    // A zero length hint leaves only room for the header
+    let start = Instant::now();
+    for path in paths {
+        DiskUtil::read(path, Some(0)).await?;
+    }
+    report.growing_time = start.elapsed();
+
+    Ok(report)
+}
+
+/// The results of reading a set of entries one by one and concurrently.
+#[derive(Debug, Clone)]
+pub struct GetManyReport {
+    /// The number of keys read.
+    pub keys: usize,
+    /// The time spent reading the entries with [Cache::get_many_owned],
+    /// which reads them one after another.
+    pub serial_time: Duration,
+    /// The time spent reading the entries with all reads issued at once.
+    pub concurrent_time: Duration,
+}
+
+/// Read the entries of `keys` from `cache` with [Cache::get_many_owned], then
+/// again with all reads issued at once.
+///
+/// Whether concurrent reads pay off depends on the strategy and the async runtime,
+/// e.g. the `blocking` runtime runs them one after another anyway.
+pub async fn compare_get_many<K, S, C>(cache: &Cache<K, S, C>, keys: &[K]) -> Result<GetManyReport>
+where
+    K: CacheKey + Eq + Hash + Sync + Send,
+    S: CacheStrategy + Send + Sync,
+    C: CompressionStrategy + Sync + Send,
+{
+    let start = Instant::now();
+    cache.get_many_owned(keys).await?;
+    let serial_time = start.elapsed();
+
+    let start = Instant::now();
+    let reads = keys
+        .iter()
+        .map(|key| cache.get_many_owned(std::slice::from_ref(key)));
+    for values in join_all(reads).await {
+        values?;
+    }
+    let concurrent_time = start.elapsed();
+
+    Ok(GetManyReport {
+        keys: keys.len(),
+        serial_time,
+        concurrent_time,
+    })
+}
+
+/// Run a single compressor over `samples`.
+async fn measure<C>(
+    algorithm: &'static str,
+    compressor: &C,
+    samples: &[Vec<u8>],
+) -> Result<CompressorReport>
+where
+    C: CompressionStrategy,
+{
+    let mut report = CompressorReport {
+        algorithm,
+        original_bytes: 0,
+        compressed_bytes: 0,
+        compress_time: Duration::ZERO,
+        decompress_time: Duration::ZERO,
+    };
+    for sample in samples {
+        let start = Instant::now();
+        let compressed = compressor.compress(Cow::Borrowed(sample)).await?;
+        report.compress_time += start.elapsed();
+
+        report.original_bytes += sample.len();
+        report.compressed_bytes += compressed.len();
+
+        let start = Instant::now();
+        compressor.decompress(compressed).await?;
+        report.decompress_time += start.elapsed();
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_compressors, compare_get_many, compare_read_preallocation};
+    use crate::{
+        async_test,
+        strategies::Memory,
+        utils::test::{create_arb_data, TempDir},
+        Cache, NO_COMPRESSION,
+    };
+
+    async_test! {
+        async fn test_compare_compressors() {
+            let samples = vec![create_arb_data(1024), create_arb_data(512)];
+            let reports = compare_compressors(&samples).await.unwrap();
+
+            let enabled = [cfg!(feature = "comp_zstd"), cfg!(feature = "comp_gzip"), cfg!(feature = "comp_brotli")];
+            assert_eq!(reports.len(), 1 + enabled.iter().filter(|&&enabled| enabled).count());
+
+            let (baseline, compressed) = reports.split_first().unwrap();
+            assert_eq!(baseline.algorithm, "none");
+            assert_eq!(baseline.ratio(), 1.0);
+            for report in compressed {
+                assert_eq!(report.original_bytes, 1536);
+                assert!(report.ratio() < 1.0);
+            }
+        }
+
+        async fn test_compare_read_preallocation() {
+            let temp_dir = TempDir::new();
+            let paths: Vec<_> = [1024, 512]
+                .into_iter()
+                .enumerate()
+                .map(|(i, len)| {
+                    let path = temp_dir.as_ref().join(i.to_string());
+                    std::fs::write(&path, create_arb_data(len)).unwrap();
+                    path
+                })
+                .collect();
+
+            let report = compare_read_preallocation(&paths).await.unwrap();
+            assert_eq!(report.files, 2);
+            assert_eq!(report.bytes, 1536);
+        }
+
+        async fn test_compare_get_many() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            let report = compare_get_many(&cache, &["foo", "bar", "baz"]).await.unwrap();
+            assert_eq!(report.keys, 3);
+        }
+    }
+}
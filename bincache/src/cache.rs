@@ -1,9 +1,156 @@
 use crate::{
-    CacheCapacity, CacheKey, CacheStrategy, CompressionStrategy, FlushableStrategy,
-    RecoverableStrategy, Result,
+    compression::CompressionLevel, entry_header::EntryHeader, CacheCapacity, CacheKey,
+    CacheStrategy, CompactReport, CompressionStrategy, DiskUtil, EntryLocation, FlushableStrategy,
+    GetInfo, ReadBufferStats, RecoverableStrategy, RecoveryReport, Result, Tier, TierStats,
 };
 
-use std::{borrow::Cow, collections::HashMap, hash::Hash};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    hash::Hash,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Limit kind reported when the uncompressed byte limit is exceeded.
+const LIMIT_KIND_BYTE_UNCOMPRESSED: &str = "Uncompressed bytes";
+/// Limit kind reported when the maximum number of keys is exceeded.
+const LIMIT_KIND_KEY: &str = "Tracked keys";
+
+/// Cache-level options, independent of the strategy.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct CacheOptions {
+    /// Enforce the strategy's byte limit against uncompressed sizes.
+    pub(crate) enforce_uncompressed_byte_limit: bool,
+    /// The maximum number of keys the cache tracks.
+    pub(crate) max_keys: Option<usize>,
+    /// The number of keys to reserve room for in the index.
+    pub(crate) capacity_hint: usize,
+    /// Store values uncompressed if compressing them doesn't make them smaller.
+    pub(crate) prefer_smaller: bool,
+    /// The order in which entries are flushed.
+    pub(crate) flush_order: FlushOrder,
+    /// The fraction of the memory byte limit to flush down to after each put.
+    pub(crate) flush_high_watermark: Option<f64>,
+}
+
+/// What to do when putting a key that already has an entry.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Replace the existing entry.
+    #[default]
+    Overwrite,
+    /// Fail with [Error::KeyExists](crate::Error::KeyExists).
+    Reject,
+    /// Keep the existing entry and don't store the new value.
+    KeepExisting,
+}
+
+/// How [Cache::flush_with_mode] treats the entries it flushes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FlushMode {
+    /// Move entries to non-volatile storage, releasing their memory.
+    #[default]
+    Move,
+    /// Copy entries to non-volatile storage, keeping them in memory for fast reads.
+    ///
+    /// The copy is written from the entry's memory, so no second in-memory copy
+    /// of the value is made. With a disk compressor, the compressed value is
+    /// allocated while it's written.
+    Copy,
+}
+
+/// The order in which [Cache::flush] and related methods visit entries.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FlushOrder {
+    /// Visit entries in no particular order.
+    #[default]
+    Arbitrary,
+    /// Visit the oldest entries first, so the most recently put ones stay in memory
+    /// if the disk tier fills up. Entries of unknown age, like recovered ones, count as oldest.
+    OldestFirst,
+}
+
+/// How a value is compressed when it's put into the cache.
+#[derive(Debug, Clone, Copy)]
+enum Compression {
+    /// Store the value as-is.
+    None,
+    /// Compress the value with the compressor's own level.
+    Default,
+    /// Compress the value with a specific level.
+    Level(CompressionLevel),
+}
+
+impl From<bool> for Compression {
+    fn from(compress: bool) -> Self {
+        match compress {
+            true => Compression::Default,
+            false => Compression::None,
+        }
+    }
+}
+
+/// The value an entry had before it was overwritten, used for rolling back.
+struct PreviousValue {
+    /// The decompressed value.
+    value: Vec<u8>,
+    /// Whether the value was stored compressed.
+    compressed: bool,
+}
+
+/// An entry tracked by the [Cache].
+#[derive(Debug)]
+struct IndexEntry<E> {
+    /// The strategy-specific entry.
+    entry: E,
+    /// Whether the entry's data was compressed by the cache's compressor.
+    compressed: bool,
+    /// The uncompressed length of the entry's data.
+    byte_len: usize,
+    /// When the entry was put into the cache, if known.
+    created_at: Option<Instant>,
+}
+
+impl<E> IndexEntry<E> {
+    /// Create an entry recovered from a previous state, where `stored_len` is
+    /// the length of the data as stored.
+    ///
+    /// Whether the entry is compressed is taken from its header. Entries stored
+    /// without one are assumed to be compressed. Their age is unknown.
+    fn recovered(entry: E, stored_len: usize, header: Option<EntryHeader>) -> Self {
+        let (compressed, byte_len) = match header {
+            Some(header) if header.compressed => (true, header.original_len as usize),
+            Some(_) => (false, stored_len),
+            None => (true, stored_len),
+        };
+        Self {
+            entry,
+            compressed,
+            byte_len,
+            created_at: None,
+        }
+    }
+
+    /// Create an entry with the same metadata, but a different strategy-specific entry.
+    fn with_entry<E2>(&self, entry: E2) -> IndexEntry<E2> {
+        IndexEntry {
+            entry,
+            compressed: self.compressed,
+            byte_len: self.byte_len,
+            created_at: self.created_at,
+        }
+    }
+
+    /// Check whether the entry was put into the cache within `max_age`.
+    fn is_fresh(&self, max_age: Duration) -> bool {
+        self.created_at
+            .map_or(false, |created_at| created_at.elapsed() <= max_age)
+    }
+}
 
 /// Binary cache.
 #[derive(Debug)]
@@ -13,9 +160,12 @@ where
     S: CacheStrategy,
     C: CompressionStrategy + Sync + Send,
 {
-    data: HashMap<K, S::CacheEntry>,
+    data: HashMap<K, IndexEntry<S::CacheEntry>>,
     strategy: S,
     compressor: Option<C>,
+    options: CacheOptions,
+    /// Sum of the uncompressed lengths of all entries.
+    uncompressed_byte_count: usize,
 }
 
 impl<K, S, C> Cache<K, S, C>
@@ -25,48 +175,672 @@ where
     C: CompressionStrategy + Sync + Send,
 {
     /// Create a new [Cache].
-    pub async fn new(mut strategy: S, compressor: Option<C>) -> Result<Cache<K, S, C>>
+    pub async fn new(strategy: S, compressor: Option<C>) -> Result<Cache<K, S, C>>
     where
         C: CompressionStrategy + Sync + Send,
     {
+        Self::with_options(strategy, compressor, CacheOptions::default()).await
+    }
+
+    /// Create a new [Cache] with the given options.
+    pub(crate) async fn with_options(
+        mut strategy: S,
+        compressor: Option<C>,
+        options: CacheOptions,
+    ) -> Result<Cache<K, S, C>> {
+        if let Some(high_watermark) = options.flush_high_watermark {
+            if !(0.0..=1.0).contains(&high_watermark) {
+                return Err(crate::Error::InvalidOption {
+                    option: "flush_high_watermark",
+                    reason: "must be between 0 and 1",
+                });
+            }
+        }
         strategy.setup().await?;
         Ok(Cache {
-            data: HashMap::new(),
+            data: HashMap::with_capacity(options.capacity_hint),
             strategy,
             compressor,
+            options,
+            uncompressed_byte_count: 0,
         })
     }
 
     /// Put an entry into the cache.
+    ///
+    /// ## Limits
+    /// Values are compressed before they are handed to the strategy, so the
+    /// strategy's byte limit applies to the *compressed* size by default.
+    /// Use [`enforce_uncompressed_byte_limit`](crate::cache_builder::CacheBuilderWithStrategy::enforce_uncompressed_byte_limit)
+    /// to bound the uncompressed size instead.
+    ///
+    /// ## Existing keys
+    /// Putting an existing key replaces its entry in place: the old entry is kept
+    /// until the new one is stored, so if storing the new entry fails, the key keeps
+    /// its old value. Strategies that can't replace entries return
+    /// [Error::Unsupported](crate::Error::Unsupported), in which case the old entry is
+    /// removed before the new one is stored, and a failed put leaves the key without
+    /// an entry. [Memory](crate::strategies::Memory), [Disk](crate::strategies::Disk)
+    /// and [Hybrid](crate::strategies::Hybrid) replace entries in place.
+    ///
+    /// ## Cancellation
+    /// Dropping the returned future, e.g. on a timeout, doesn't leave an unindexed
+    /// file behind: the built-in strategies remove a file whose write was cancelled.
+    /// The new entry is indexed in the same poll its write completes in. Until then,
+    /// an existing entry keeps its old value, except with strategies that can't
+    /// replace entries, see above.
+    ///
+    /// ## Zero-length values
+    /// Empty values are valid entries. They count towards the key limits, are reported
+    /// by [`exists`](Self::exists) and returned by [`get`](Self::get), and an
+    /// empty file is recovered like any other entry. Use [`try_get`](Self::try_get)
+    /// to tell them apart from missing keys.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(key = %key.to_key(), byte_len = tracing::field::Empty)
+        )
+    )]
     pub async fn put<'a, V>(&mut self, key: K, value: V) -> Result<()>
     where
+        S::CacheEntry: Sync,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        self.put_entry(key, None, value.into(), Compression::Default)
+            .await?;
+        Ok(())
+    }
+
+    /// Put an entry into the cache, deciding what happens to an existing entry with `policy`.
+    /// Returns `true` if the value was stored, or `false` if the existing entry was kept.
+    ///
+    /// Useful for write-once caches, where overwriting an entry by accident is a bug.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(key = %key.to_key(), byte_len = tracing::field::Empty)
+        )
+    )]
+    pub async fn put_with_policy<'a, V>(
+        &mut self,
+        key: K,
+        value: V,
+        policy: OnConflict,
+    ) -> Result<bool>
+    where
+        S::CacheEntry: Sync,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        if self.data.contains_key(&key) {
+            match policy {
+                OnConflict::Overwrite => {}
+                OnConflict::Reject => return Err(crate::Error::KeyExists),
+                OnConflict::KeepExisting => return Ok(false),
+            }
+        }
+        self.put_entry(key, None, value.into(), Compression::Default)
+            .await?;
+        Ok(true)
+    }
+
+    /// Put an entry into the cache only if the key doesn't have one yet.
+    /// Returns `true` if the value was stored, or `false` if an entry already existed.
+    ///
+    /// The existing entry is left untouched, so keys that are already present cost no write.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(key = %key.to_key(), byte_len = tracing::field::Empty)
+        )
+    )]
+    pub async fn put_if_absent<'a, V>(&mut self, key: K, value: V) -> Result<bool>
+    where
+        S::CacheEntry: Sync,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        self.put_with_policy(key, value, OnConflict::KeepExisting)
+            .await
+    }
+
+    /// Put an entry into the cache, returning where the strategy stored it.
+    ///
+    /// Useful with tiered strategies like [Hybrid](crate::strategies::Hybrid)
+    /// to see whether a value landed in memory or on disk.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(key = %key.to_key(), byte_len = tracing::field::Empty)
+        )
+    )]
+    pub async fn put_located<'a, V>(&mut self, key: K, value: V) -> Result<EntryLocation>
+    where
+        S::CacheEntry: Sync,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        self.put_entry(key, None, value.into(), Compression::Default)
+            .await
+    }
+
+    /// Put an entry into the cache without compressing it.
+    ///
+    /// Useful for data that is already compressed (e.g. images or archives),
+    /// where compressing it again wastes CPU and may even grow it.
+    /// The entry is marked as uncompressed, so [`get`](Self::get) skips decompression.
+    ///
+    /// Strategies storing entries in files, like [Disk](crate::strategies::Disk),
+    /// keep the marker in the entry's header, so it survives recovery.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(key = %key.to_key(), byte_len = tracing::field::Empty)
+        )
+    )]
+    pub async fn put_uncompressed<'a, V>(&mut self, key: K, value: V) -> Result<()>
+    where
+        S::CacheEntry: Sync,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        self.put_entry(key, None, value.into(), Compression::None)
+            .await?;
+        Ok(())
+    }
+
+    /// Put an entry into the cache, compressing it with a specific compression level.
+    ///
+    /// Useful to compress large, rarely read entries harder than the rest, while
+    /// other puts stay fast. Compressors that don't support levels ignore it.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(key = %key.to_key(), byte_len = tracing::field::Empty)
+        )
+    )]
+    pub async fn put_with_level<'a, V>(
+        &mut self,
+        key: K,
+        value: V,
+        level: CompressionLevel,
+    ) -> Result<()>
+    where
+        S::CacheEntry: Sync,
         V: Into<Cow<'a, [u8]>> + Send,
     {
-        let value: Cow<'_, [u8]> = self.compressor.compress(value.into()).await?;
+        self.put_entry(key, None, value.into(), Compression::Level(level))
+            .await?;
+        Ok(())
+    }
+
+    /// Put an entry into the cache, using a pre-computed key string.
+    ///
+    /// The strategy stores the entry under `key_str` instead of calling
+    /// [`to_key`](CacheKey::to_key) on the key, which is useful when the key string
+    /// is expensive to derive, e.g. a content hash that's already at hand.
+    /// The entry is still indexed under `key`.
+    ///
+    /// ## Disclaimer
+    /// `key_str` should match what `key.to_key()` would return. Otherwise, entries
+    /// recovered from a previous state are indexed under whatever key `key_str` maps to.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(key = %key_str, byte_len = tracing::field::Empty)
+        )
+    )]
+    pub async fn put_raw_key<'a, V>(&mut self, key: K, key_str: String, value: V) -> Result<()>
+    where
+        S::CacheEntry: Sync,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        self.put_entry(key, Some(&key_str), value.into(), Compression::Default)
+            .await?;
+        Ok(())
+    }
+
+    /// Put an entry into the cache from a [`Bytes`](bytes::Bytes) buffer.
+    ///
+    /// The buffer is borrowed for compression and storage, so it doesn't need
+    /// to be converted into a `Vec<u8>` first.
+    #[cfg(feature = "bytes")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(key = %key.to_key(), byte_len = value.len())
+        )
+    )]
+    pub async fn put_bytes(&mut self, key: K, value: bytes::Bytes) -> Result<()>
+    where
+        S::CacheEntry: Sync,
+    {
+        self.put(key, value.as_ref()).await
+    }
+
+    /// Put multiple entries into the cache, returning how many were inserted.
+    ///
+    /// Insertion stops at the first entry that would exceed a limit; the entries
+    /// inserted before it stay in the cache and are included in the returned count.
+    /// Any other error is propagated.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(inserted = tracing::field::Empty))
+    )]
+    pub async fn extend<I>(&mut self, entries: I) -> Result<usize>
+    where
+        S::CacheEntry: Sync,
+        I: IntoIterator<Item = (K, Vec<u8>)>,
+    {
+        let mut inserted = 0;
+        for (key, value) in entries {
+            match self.put(key, value).await {
+                Ok(()) => inserted += 1,
+                Err(crate::Error::LimitExceeded { .. }) => break,
+                Err(err) => return Err(err),
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("inserted", inserted);
+        Ok(inserted)
+    }
+
+    /// Put multiple entries into the cache, either all of them or none.
+    ///
+    /// If any entry fails, e.g. because it would exceed a limit, the entries put
+    /// before it are rolled back: new keys are deleted and overwritten keys get
+    /// their previous value back.
+    ///
+    /// ## Disclaimer
+    /// Rolling back is best-effort. If it fails as well, the original error is
+    /// still returned. Restored entries count as freshly put.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(entries = entries.len()))
+    )]
+    pub async fn put_transaction(&mut self, entries: Vec<(K, Vec<u8>)>) -> Result<()>
+    where
+        S::CacheEntry: Sync,
+        K: Clone,
+    {
+        let mut applied = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            // Take out the previous entry, so it can be restored when rolling back
+            let previous = match self.remove_entry(&key) {
+                Ok(entry) => {
+                    let compressed = entry.compressed;
+                    match self.take_entry(entry).await {
+                        Ok(value) => Some(PreviousValue { value, compressed }),
+                        Err(err) => {
+                            self.rollback(applied).await;
+                            return Err(err);
+                        }
+                    }
+                }
+                Err(_) => None,
+            };
+            applied.push((key.clone(), previous));
 
-        let entry = self.strategy.put(&key, value).await?;
-        self.data.insert(key, entry);
+            if let Err(err) = self
+                .put_entry(key, None, value.into(), Compression::Default)
+                .await
+            {
+                self.rollback(applied).await;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Append data to an entry's value, without reading it back first.
+    ///
+    /// Useful for accumulating buffers, like logs. Returns [Error::KeyNotFound](crate::Error::KeyNotFound)
+    /// if the key doesn't exist, see [append_or_create](Self::append_or_create).
+    ///
+    /// Compressed data can't be appended to, so this returns [Error::Unsupported](crate::Error::Unsupported)
+    /// for entries compressed by the cache's compressor. Store entries with
+    /// [put_uncompressed](Self::put_uncompressed) to append to them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(key = %key.to_key(), byte_len = data.len())
+        )
+    )]
+    pub async fn append(&mut self, key: K, data: &[u8]) -> Result<()>
+    where
+        S::CacheEntry: Send,
+    {
+        let entry = self.data.get(&key).ok_or(crate::Error::KeyNotFound)?;
+        if entry.compressed && self.compressor.is_some() {
+            return Err(crate::Error::Unsupported {
+                operation: "append to a compressed entry",
+            });
+        }
+        self.check_limits(&key, entry.byte_len.saturating_add(data.len()))?;
+
+        let entry = self.data.get_mut(&key).ok_or(crate::Error::KeyNotFound)?;
+        self.strategy.append(&mut entry.entry, data).await?;
+        entry.byte_len += data.len();
+        self.uncompressed_byte_count = self.uncompressed_byte_count.saturating_add(data.len());
         Ok(())
     }
 
+    /// Append data to an entry's value, creating the entry if it doesn't exist.
+    ///
+    /// Created entries are stored uncompressed, so they can be appended to later on.
+    pub async fn append_or_create(&mut self, key: K, data: &[u8]) -> Result<()>
+    where
+        S::CacheEntry: Sync,
+        S::CacheEntry: Send,
+    {
+        if self.data.contains_key(&key) {
+            return self.append(key, data).await;
+        }
+        self.put_entry(key, None, Cow::Borrowed(data), Compression::None)
+            .await?;
+        Ok(())
+    }
+
+    /// Estimate the number of bytes a value would occupy once stored.
+    ///
+    /// This runs the configured compressor on the value without inserting it,
+    /// so it costs as much CPU as a regular [`put`](Self::put).
+    pub async fn estimated_stored_size(&self, value: &[u8]) -> Result<usize> {
+        let value = self.compressor.compress(Cow::Borrowed(value)).await?;
+        Ok(value.len())
+    }
+
     /// Get an entry from the cache.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(key = %key.to_key()))
+    )]
     pub async fn get(&self, key: K) -> Result<Cow<'_, [u8]>> {
+        let entry = self
+            .data
+            .get(&key)
+            .ok_or(crate::Error::KeyNotFound)
+            .map_err(trace_error)?;
+        let value = self.strategy.get(&entry.entry).await?;
+        self.decompress(entry.compressed, value).await
+    }
+
+    /// Get an entry from the cache, if it exists.
+    ///
+    /// Unlike [`get`](Self::get), a missing key returns `None` instead of an error,
+    /// while an entry storing zero bytes returns `Some` with an empty value.
+    pub async fn try_get(&self, key: K) -> Result<Option<Cow<'_, [u8]>>> {
+        self.get_existing(&key).await
+    }
+
+    /// Get an entry from the cache, inserting the value produced by `f` if it doesn't exist.
+    ///
+    /// See [`get_or_try_insert_with`](Self::get_or_try_insert_with) for producers that can fail.
+    pub async fn get_or_insert_with<F, Fut>(&mut self, key: K, f: F) -> Result<Cow<'_, [u8]>>
+    where
+        S::CacheEntry: Sync,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Vec<u8>>,
+    {
+        self.get_or_try_insert_with(key, || async { Ok::<_, Infallible>(f().await) })
+            .await
+    }
+
+    /// Get an entry from the cache, inserting the value produced by `f` if it doesn't exist.
+    ///
+    /// The producer only runs for missing keys. If it fails, nothing is stored and
+    /// its error is returned as [Error::CustomError](crate::Error::CustomError).
+    /// This fits read-through caches in front of databases or remote services.
+    pub async fn get_or_try_insert_with<F, Fut, E>(&mut self, key: K, f: F) -> Result<Cow<'_, [u8]>>
+    where
+        S::CacheEntry: Sync,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = std::result::Result<Vec<u8>, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        if self.data.contains_key(&key) {
+            return self.get(key).await;
+        }
+
+        let value = f()
+            .await
+            .map_err(|err| crate::Error::CustomError(Box::new(err)))?;
+        self.put(key, value.as_slice()).await?;
+        Ok(Cow::Owned(value))
+    }
+
+    /// Get an entry's stored bytes, without decompressing them.
+    ///
+    /// This bypasses the compressor, so it can be used to inspect or salvage
+    /// entries that fail to decompress. Whether the bytes are compressed depends
+    /// on how the entry was put.
+    pub async fn get_raw(&self, key: K) -> Result<Cow<'_, [u8]>> {
+        let entry = self.data.get(&key).ok_or(crate::Error::KeyNotFound)?;
+        self.strategy.get(&entry.entry).await
+    }
+
+    /// Get an entry from the cache as a shared buffer.
+    ///
+    /// Unlike [`get`](Self::get), the returned value doesn't borrow the cache and
+    /// is cheap to clone. Strategies storing shared buffers, like
+    /// [Memory](crate::strategies::Memory), hand out uncompressed entries without
+    /// copying them. Otherwise the value is copied into a new buffer.
+    pub async fn get_shared(&self, key: K) -> Result<Arc<[u8]>> {
         let entry = self.data.get(&key).ok_or(crate::Error::KeyNotFound)?;
-        let value = self.strategy.get(entry).await?;
-        self.compressor.decompress(value).await
+        if !entry.compressed || self.compressor.is_none() {
+            if let Some(value) = self.strategy.get_shared(&entry.entry) {
+                return Ok(value);
+            }
+        }
+        let value = self.strategy.get(&entry.entry).await?;
+        let value = self.decompress(entry.compressed, value).await?;
+        Ok(Arc::from(value))
+    }
+
+    /// Get an entry from the cache, along with details about the read.
+    ///
+    /// Useful for diagnosing slow reads, e.g. to see whether an entry was read
+    /// from disk or how long decompressing it took.
+    pub async fn get_detailed(&self, key: K) -> Result<(Cow<'_, [u8]>, GetInfo)> {
+        let entry = self.data.get(&key).ok_or(crate::Error::KeyNotFound)?;
+        let location = self.strategy.entry_location(&entry.entry);
+        let value = self.strategy.get(&entry.entry).await?;
+        let stored_len = value.len();
+
+        let start = Instant::now();
+        let value = self.decompress(entry.compressed, value).await?;
+        let decompress_time = start.elapsed();
+
+        let info = GetInfo {
+            location,
+            stored_len,
+            byte_len: value.len(),
+            decompressed: entry.compressed && self.compressor.is_some(),
+            decompress_time,
+        };
+        Ok((value, info))
+    }
+
+    /// Get an entry from the cache, if it was put within `max_age`.
+    ///
+    /// Returns `None` if the entry is older than `max_age`, even though it still
+    /// exists. Entries recovered from a previous state have no known age, so they
+    /// are never considered fresh.
+    pub async fn get_if_fresh(&self, key: K, max_age: Duration) -> Result<Option<Cow<'_, [u8]>>> {
+        let entry = self.data.get(&key).ok_or(crate::Error::KeyNotFound)?;
+        if !entry.is_fresh(max_age) {
+            return Ok(None);
+        }
+        let value = self.strategy.get(&entry.entry).await?;
+        Ok(Some(self.decompress(entry.compressed, value).await?))
+    }
+
+    /// Get an owned copy of an entry from the cache.
+    ///
+    /// Unlike [`get`](Self::get), the returned value doesn't borrow the cache.
+    /// Entries held in memory are copied, which costs an allocation and a copy
+    /// of the entry's bytes.
+    pub async fn get_owned(&self, key: K) -> Result<Vec<u8>> {
+        Ok(self.get(key).await?.into_owned())
+    }
+
+    /// Get an entry from the cache, or an empty value if it doesn't exist.
+    ///
+    /// Unlike [`get`](Self::get), a missing key isn't an error.
+    /// Errors from the strategy or the compressor are still propagated.
+    /// A missing key and an empty entry look the same here, see [`try_get`](Self::try_get).
+    pub async fn get_or_default(&self, key: K) -> Result<Cow<'_, [u8]>> {
+        Ok(self.get_existing(&key).await?.unwrap_or_default())
+    }
+
+    /// Get owned copies of multiple entries from the cache.
+    /// Returns the values in the order of the given keys, with `None` for missing entries.
+    pub async fn get_many_owned(&self, keys: &[K]) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            let value = self.get_existing(key).await?;
+            values.push(value.map(Cow::into_owned));
+        }
+        Ok(values)
+    }
+
+    /// Get owned copies of multiple entries from the cache, mapped by their keys.
+    /// Missing entries are omitted.
+    pub async fn get_map(&self, keys: impl IntoIterator<Item = K>) -> Result<HashMap<K, Vec<u8>>> {
+        let mut values = HashMap::new();
+        for key in keys {
+            if let Some(value) = self.get_existing(&key).await? {
+                let value = value.into_owned();
+                values.insert(key, value);
+            }
+        }
+        Ok(values)
     }
 
     /// Take an entry from the cache, removing it.
     pub async fn take(&mut self, key: K) -> Result<Vec<u8>> {
-        let entry = self.data.remove(&key).ok_or(crate::Error::KeyNotFound)?;
-        let value = self.strategy.take(entry).await?;
-        Ok(self.compressor.decompress(value.into()).await?.into_owned())
+        let entry = self.remove_entry(&key)?;
+        self.take_entry(entry).await
     }
 
     /// Delete an entry from the cache.
     pub async fn delete(&mut self, key: K) -> Result<()> {
-        let entry = self.data.remove(&key).ok_or(crate::Error::KeyNotFound)?;
-        self.strategy.delete(entry).await
+        let entry = self.remove_entry(&key)?;
+        self.strategy.delete(entry.entry).await
+    }
+
+    /// Remove an entry from the cache, if it exists.
+    /// Returns whether an entry was removed.
+    ///
+    /// Unlike [`delete`](Self::delete), a missing key isn't an error.
+    pub async fn remove(&mut self, key: K) -> Result<bool> {
+        if !self.data.contains_key(&key) {
+            return Ok(false);
+        }
+        self.delete(key).await?;
+        Ok(true)
+    }
+
+    /// Replace an entry, returning the previous value.
+    /// Returns `None` if the entry didn't exist.
+    ///
+    /// The previous entry is taken before the new one is put, so its backing
+    /// data is cleaned up properly.
+    ///
+    /// ## Disclaimer
+    /// If putting the new value fails, the previous entry has already been removed.
+    pub async fn swap<'a, V>(&mut self, key: K, value: V) -> Result<Option<Vec<u8>>>
+    where
+        S::CacheEntry: Sync,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        let previous = match self.data.contains_key(&key) {
+            true => {
+                let entry = self.remove_entry(&key)?;
+                Some(self.take_entry(entry).await?)
+            }
+            false => None,
+        };
+        self.put(key, value).await?;
+        Ok(previous)
+    }
+
+    /// Take multiple entries from the cache, removing them.
+    /// Returns the values in the order of the given keys, with `None` for missing entries.
+    ///
+    /// ## Disclaimer
+    /// If an error occurs, the entry that was being taken is lost. Entries taken
+    /// before it are removed from the cache, entries after it remain.
+    pub async fn take_many(&mut self, keys: &[K]) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            let value = match self.remove_entry(key) {
+                Ok(entry) => Some(self.take_entry(entry).await?),
+                Err(_) => None,
+            };
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    /// Take all entries from the cache, removing them.
+    /// Returns the keys along with their values, in no particular order.
+    ///
+    /// ## Disclaimer
+    /// If an error occurs, the entry that was being taken is lost. Entries not
+    /// yet processed remain in the cache.
+    pub async fn drain(&mut self) -> Result<Vec<(K, Vec<u8>)>> {
+        let mut entries = Vec::with_capacity(self.data.len());
+        let mut data = std::mem::take(&mut self.data).into_iter();
+        self.uncompressed_byte_count = 0;
+
+        while let Some((key, entry)) = data.next() {
+            match self.take_entry(entry).await {
+                Ok(value) => entries.push((key, value)),
+                Err(err) => {
+                    // Keep the remaining entries
+                    for (key, entry) in data {
+                        self.insert(key, entry);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Copy all entries out of the cache.
+    /// Returns the keys along with their values, in no particular order.
+    ///
+    /// The cache is borrowed immutably for the whole call, so it can't change in
+    /// between and the copy reflects a single point in time. To export a cache that's
+    /// shared behind a read-write lock, call this while holding the read lock.
+    pub async fn snapshot(&self) -> Result<Vec<(K, Vec<u8>)>>
+    where
+        K: Clone,
+    {
+        let mut entries = Vec::with_capacity(self.data.len());
+        for (key, entry) in &self.data {
+            let value = self.strategy.get(&entry.entry).await?;
+            let value = self.decompress(entry.compressed, value).await?;
+            entries.push((key.clone(), value.into_owned()));
+        }
+        Ok(entries)
     }
 
     /// Check if an entry exists.
@@ -74,15 +848,660 @@ where
         self.data.contains_key(&key)
     }
 
+    /// Check if an entry has a copy on disk that survives a restart.
+    ///
+    /// With tiered strategies like [Hybrid](crate::strategies::Hybrid), entries
+    /// held in memory are lost on restart even though they [exist](Self::exists).
+    /// The same goes for [spilled](EntryLocation::Spilled) entries.
+    /// This only inspects the index, so no IO is done.
+    pub fn is_durable(&self, key: K) -> bool {
+        self.data.get(&key).map_or(false, |entry| {
+            self.strategy.entry_location(&entry.entry).is_durable()
+        })
+    }
+
+    /// Get the path of the file holding an entry, e.g. to hand it to an external tool,
+    /// along with the offset the value starts at in the file.
+    ///
+    /// Returns `None` if the entry doesn't exist, isn't stored in a file, or is
+    /// compressed, since the file's contents wouldn't be the entry's value then.
+    pub fn entry_path(&self, key: K) -> Option<(PathBuf, u64)> {
+        let entry = self.data.get(&key)?;
+        if entry.compressed && self.compressor.is_some() {
+            return None;
+        }
+        self.strategy
+            .entry_path(&entry.entry)
+            .map(|(path, offset)| (path.to_path_buf(), offset))
+    }
+
+    /// Check if all of the given entries exist.
+    pub fn contains_all(&self, keys: &[K]) -> bool {
+        keys.iter().all(|key| self.data.contains_key(key))
+    }
+
+    /// Check if any of the given entries exist.
+    pub fn contains_any(&self, keys: &[K]) -> bool {
+        keys.iter().any(|key| self.data.contains_key(key))
+    }
+
+    /// Get the keys of all given entries that don't exist.
+    pub fn missing_keys<'k>(&self, keys: &'k [K]) -> Vec<&'k K> {
+        keys.iter()
+            .filter(|key| !self.data.contains_key(key))
+            .collect()
+    }
+
+    /// Get all keys along with the number of bytes their entries occupy,
+    /// sorted from largest to smallest.
+    ///
+    /// Sizes are taken from the tracked entries, so no data is read. If the
+    /// strategy doesn't track entry sizes, the uncompressed size is used instead.
+    pub fn entries_by_size(&self) -> Vec<(&K, usize)> {
+        let mut entries: Vec<_> = self
+            .data
+            .iter()
+            .map(|(key, entry)| {
+                let byte_len = self
+                    .strategy
+                    .stored_byte_len(&entry.entry)
+                    .unwrap_or(entry.byte_len);
+                (key, byte_len)
+            })
+            .collect();
+        entries.sort_by_key(|&(_, byte_len)| std::cmp::Reverse(byte_len));
+        entries
+    }
+
+    /// Get the number of keys and bytes stored in each tier.
+    ///
+    /// Computed from the tracked entries, so no IO is done. Entries whose location
+    /// the strategy doesn't report aren't counted. Sizes fall back to the
+    /// uncompressed size if the strategy doesn't track entry sizes.
+    pub fn tier_stats(&self) -> TierStats {
+        let mut stats = TierStats::default();
+        for entry in self.data.values() {
+            let byte_len = self
+                .strategy
+                .stored_byte_len(&entry.entry)
+                .unwrap_or(entry.byte_len);
+            match self.strategy.entry_location(&entry.entry) {
+                EntryLocation::Memory => {
+                    stats.memory_keys += 1;
+                    stats.memory_bytes += byte_len;
+                }
+                EntryLocation::Disk | EntryLocation::Spilled => {
+                    stats.disk_keys += 1;
+                    stats.disk_bytes += byte_len;
+                }
+                EntryLocation::MemoryAndDisk => {
+                    stats.memory_keys += 1;
+                    stats.memory_bytes += byte_len;
+                    stats.disk_keys += 1;
+                    stats.disk_bytes += byte_len;
+                }
+                EntryLocation::Unknown => {}
+            }
+        }
+        stats
+    }
+
+    /// Get the number of bytes the cache directory occupies on disk.
+    ///
+    /// Unlike [`capacity`](Self::capacity), this walks the directory and includes
+    /// file system overhead (on Unix) and untracked files, which matters when
+    /// planning capacity on nearly full partitions. The `lost+found` directory is
+    /// only included if `include_lost_found` is set.
+    ///
+    /// Returns 0 if the strategy doesn't store entries on disk.
+    pub fn disk_usage(&self, include_lost_found: bool) -> Result<u64> {
+        match self.strategy.cache_dir() {
+            Some(cache_dir) => DiskUtil::dir_usage(cache_dir, include_lost_found),
+            None => Ok(0),
+        }
+    }
+
+    /// Get the total number of bytes occupied by all tracked entries, across all tiers.
+    ///
+    /// This is summed from the tracked entries rather than the strategy's running
+    /// counters, so it can be used to cross-check [`capacity`](Self::capacity).
+    /// If the strategy doesn't track entry sizes, the uncompressed size is used instead.
+    pub fn total_stored_bytes(&self) -> usize {
+        self.data
+            .values()
+            .map(|entry| {
+                self.strategy
+                    .stored_byte_len(&entry.entry)
+                    .unwrap_or(entry.byte_len)
+            })
+            .sum()
+    }
+
+    /// Verify the integrity of all entries without returning their data.
+    /// Returns the keys of entries whose backing data is missing or has the wrong size.
+    ///
+    /// For disk-backed strategies this checks the size of every entry file,
+    /// so it can take a while on large caches.
+    pub async fn verify(&self) -> Result<Vec<K>>
+    where
+        K: Clone,
+        S: Sync,
+    {
+        let mut corrupted_keys = Vec::new();
+        for (key, entry) in self.data.iter() {
+            if !self.strategy.verify(&entry.entry).await? {
+                corrupted_keys.push(key.clone());
+            }
+        }
+        Ok(corrupted_keys)
+    }
+
+    /// Remove all entries whose backing data is missing or has the wrong size.
+    /// Returns the keys of the removed entries.
+    ///
+    /// This [verifies](Self::verify) every entry and deletes the corrupted ones,
+    /// so the index and the strategy's limits match the stored data again.
+    pub async fn remove_corrupted(&mut self) -> Result<Vec<K>>
+    where
+        K: Clone,
+        S: Sync,
+    {
+        let corrupted_keys = self.verify().await?;
+        for key in &corrupted_keys {
+            let entry = self.remove_entry(key)?;
+            self.strategy.delete(entry.entry).await?;
+        }
+        Ok(corrupted_keys)
+    }
+
+    /// Tidy up the cache's storage, e.g. for long-lived caches.
+    ///
+    /// This removes empty directories from the cache directory, and deletes files
+    /// in the `lost+found` directory and temporary files left behind by interrupted
+    /// writes that haven't been modified within `max_age`. The strategy's usage
+    /// counters are recomputed from the index, in case they drifted.
+    ///
+    /// Entries are kept, even if their data is missing. Use
+    /// [remove_corrupted](Self::remove_corrupted) to remove those.
+    pub async fn compact(&mut self, max_age: Duration) -> Result<CompactReport> {
+        let mut report = CompactReport::default();
+        if let Some(cache_dir) = self.strategy.cache_dir() {
+            report.removed_empty_dirs = DiskUtil::remove_empty_dirs(cache_dir)?;
+            report.removed_lost_found = DiskUtil::remove_stale_lost_found(cache_dir, max_age)?;
+            report.removed_temp_files = DiskUtil::remove_stale_temp_files(cache_dir, max_age)?;
+        }
+
+        self.strategy
+            .recount(self.data.values().map(|entry| &entry.entry).collect());
+        self.uncompressed_byte_count = self.data.values().map(|entry| entry.byte_len).sum();
+
+        Ok(report)
+    }
+
+    /// Re-compress all entries using a different compression strategy.
+    ///
+    /// Every entry is decompressed with the current compressor, compressed with
+    /// the new one and stored again under the same key, replacing the old value
+    /// only once the new one is stored. Like [put](Self::put), values are stored
+    /// uncompressed if [prefer_smaller](crate::cache_builder::CacheBuilderWithCompressionAndStrategy::prefer_smaller)
+    /// is set and compressing them doesn't make them smaller.
+    ///
+    /// Entries stored uncompressed, e.g. with [put_uncompressed](Self::put_uncompressed),
+    /// are left as they are.
+    ///
+    /// ## Disclaimer
+    /// The cache is consumed. If an error occurs, entries not yet processed are
+    /// still stored with the old compressor, so persistent strategies can recover them
+    /// along with the re-compressed ones.
+    pub async fn recompress<C2>(self, compressor: C2) -> Result<Cache<K, S, C2>>
+    where
+        S::CacheEntry: Sync,
+        K: ToOwned<Owned = K>,
+        C2: CompressionStrategy + Sync + Send,
+    {
+        let old_compressor = self.compressor;
+        let mut cache = Cache {
+            data: self.data,
+            strategy: self.strategy,
+            compressor: Some(compressor),
+            options: self.options,
+            uncompressed_byte_count: self.uncompressed_byte_count,
+        };
+
+        let keys: Vec<K> = cache.data.keys().map(|key| key.to_owned()).collect();
+        for key in keys {
+            let Some(entry) = cache.data.get(&key) else {
+                continue;
+            };
+            if !entry.compressed {
+                continue;
+            }
+            let created_at = entry.created_at;
+            let value = cache.strategy.get(&entry.entry).await?;
+            let value = decompress_with(&old_compressor, entry.compressed, value)
+                .await?
+                .into_owned();
+            cache
+                .put_entry(key.to_owned(), None, value.into(), Compression::Default)
+                .await?;
+
+            // Re-compressing doesn't change the entry's age
+            if let Some(entry) = cache.data.get_mut(&key) {
+                entry.created_at = created_at;
+            }
+        }
+
+        Ok(cache)
+    }
+
+    /// Close the cache, tearing down its strategy.
+    ///
+    /// Unlike dropping the cache, this gives the strategy a chance to release
+    /// its resources and report errors. Stored entries are kept, so persistent
+    /// strategies can recover them later.
+    pub async fn close(mut self) -> Result<()> {
+        self.strategy.teardown().await
+    }
+
+    /// Deconstruct the cache into its strategy and compressor.
+    ///
+    /// Useful to rebuild a cache around a warmed strategy, e.g. with a different compressor.
+    ///
+    /// ## Disclaimer
+    /// The index is dropped without touching stored entries, so the strategy keeps
+    /// its data and still counts the entries towards its limits.
+    pub fn into_parts(self) -> (S, Option<C>) {
+        (self.strategy, self.compressor)
+    }
+
+    /// Migrate all entries to a different cache strategy.
+    ///
+    /// The new strategy is set up, then every entry is copied from the current
+    /// strategy to the new one under the same key. Stored data is copied as-is,
+    /// so entries are not re-compressed. Once all entries are copied, they are
+    /// deleted from the current strategy.
+    ///
+    /// ## Disclaimer
+    /// The cache is consumed. If an error occurs while copying, the copies are
+    /// deleted from the new strategy again and the current strategy is left
+    /// untouched, so persistent strategies can recover its entries. Failing to
+    /// delete an entry from the current strategy doesn't fail the migration.
+    pub async fn migrate_to<S2>(mut self, mut strategy: S2) -> Result<Cache<K, S2, C>>
+    where
+        S2: CacheStrategy + Send,
+    {
+        strategy.setup().await?;
+        let entries: Vec<_> = self.data.drain().collect();
+
+        let mut copies = Vec::with_capacity(entries.len());
+        for (key, entry) in &entries {
+            let header = EntryHeader {
+                compressed: entry.compressed && self.compressor.is_some(),
+                original_len: entry.byte_len as u64,
+                ..Default::default()
+            };
+            let copy = match self.strategy.get(&entry.entry).await {
+                Ok(value) => strategy.put_with_header(key, value, &header).await,
+                Err(err) => Err(err),
+            };
+            match copy {
+                Ok(copy) => copies.push(copy),
+                Err(err) => {
+                    // Roll back, so the new strategy doesn't hold partial data
+                    for copy in copies {
+                        _ = strategy.delete(copy).await;
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        let mut data = HashMap::with_capacity(entries.len());
+        for ((key, entry), copy) in entries.into_iter().zip(copies) {
+            let new_entry = entry.with_entry(copy);
+            _ = self.strategy.delete(entry.entry).await;
+            data.insert(key, new_entry);
+        }
+
+        Ok(Cache {
+            data,
+            strategy,
+            compressor: self.compressor,
+            options: self.options,
+            uncompressed_byte_count: self.uncompressed_byte_count,
+        })
+    }
+
+    /// Copy all entries into another cache, returning how many were copied.
+    ///
+    /// Unlike [`migrate_to`](Self::migrate_to), this cache is left untouched, so it
+    /// can keep serving while `dest` is warmed up. Values are decompressed and put
+    /// into `dest`, so they are compressed with its compressor and count towards its limits.
+    ///
+    /// ## Disclaimer
+    /// If an error occurs, entries copied before it remain in `dest`.
+    pub async fn copy_into<S2, C2>(&self, dest: &mut Cache<K, S2, C2>) -> Result<usize>
+    where
+        K: Clone,
+        S2: CacheStrategy + Send,
+        S2::CacheEntry: Sync,
+        C2: CompressionStrategy + Sync + Send,
+    {
+        let mut copied = 0;
+        for (key, entry) in &self.data {
+            let value = self.strategy.get(&entry.entry).await?;
+            let value = self.decompress(entry.compressed, value).await?;
+            dest.put(key.clone(), value).await?;
+            copied += 1;
+        }
+        Ok(copied)
+    }
+
+    /// Whether the cache's entries survive a restart.
+    ///
+    /// See [CacheStrategy::is_persistent].
+    pub fn is_persistent(&self) -> bool {
+        self.strategy.is_persistent()
+    }
+
     /// Get the cache capacity.
     pub fn capacity(&self) -> Option<CacheCapacity> {
         self.strategy.get_cache_capacity()
     }
 
+    /// Get the capacity of a single storage tier.
+    ///
+    /// Returns None if the strategy doesn't store entries in `tier` or no limit was set for it.
+    pub fn tier_capacity(&self, tier: Tier) -> Option<CacheCapacity> {
+        self.strategy.tier_capacity(tier)
+    }
+
+    /// Get the hit and miss counts of the strategy's read buffer.
+    ///
+    /// Returns None if the strategy doesn't buffer reads, see
+    /// [Disk::with_read_buffer](crate::strategies::Disk::with_read_buffer).
+    pub fn read_buffer_stats(&self) -> Option<ReadBufferStats> {
+        self.strategy.read_buffer_stats()
+    }
+
+    /// Put an entry into the strategy and index it.
+    /// Returns where the strategy stored the entry.
+    ///
+    /// The strategy stores the entry under `key_str` if given, otherwise under `key`.
+    async fn put_entry(
+        &mut self,
+        key: K,
+        key_str: Option<&str>,
+        value: Cow<'_, [u8]>,
+        compression: Compression,
+    ) -> Result<EntryLocation>
+    where
+        S::CacheEntry: Sync,
+    {
+        let byte_len = value.len();
+        trace_byte_len(byte_len);
+        self.check_limits(&key, byte_len).map_err(trace_error)?;
+
+        let (value, compress) = match compression {
+            Compression::None => (value, false),
+            _ if self.options.prefer_smaller && self.compressor.is_some() => {
+                let compressed = self
+                    .compress(Cow::Borrowed(&value), compression)
+                    .await?
+                    .into_owned();
+                if compressed.len() < value.len() {
+                    (Cow::Owned(compressed), true)
+                } else {
+                    (value, false)
+                }
+            }
+            _ => (self.compress(value, compression).await?, true),
+        };
+
+        // Strategies storing files write the header, so entries can be recovered as-is
+        let header = EntryHeader {
+            compressed: compress && self.compressor.is_some(),
+            original_len: byte_len as u64,
+            ..Default::default()
+        };
+
+        // An existing entry under the same key stays in place until it's replaced,
+        // so it survives a failed or cancelled put
+        let replaced = match self.data.get(&key) {
+            Some(old) => {
+                let value = Cow::Borrowed(value.as_ref());
+                Some(match key_str {
+                    Some(key_str) => {
+                        self.strategy
+                            .replace_with_header(&key_str, &old.entry, value, &header)
+                            .await
+                    }
+                    None => {
+                        self.strategy
+                            .replace_with_header(&key, &old.entry, value, &header)
+                            .await
+                    }
+                })
+            }
+            None => None,
+        };
+        let entry = match replaced {
+            Some(Err(crate::Error::Unsupported { .. })) | None => {
+                // The strategy can't replace entries, so the old one has to go first
+                if let Ok(old_entry) = self.remove_entry(&key) {
+                    self.strategy.delete(old_entry.entry).await?;
+                }
+                match key_str {
+                    Some(key_str) => {
+                        self.strategy
+                            .put_with_header(&key_str, value, &header)
+                            .await
+                    }
+                    None => self.strategy.put_with_header(&key, value, &header).await,
+                }
+            }
+            Some(result) => result,
+        }
+        .map_err(trace_error)?;
+        let location = self.strategy.entry_location(&entry);
+
+        // Any replaced entry is gone, and the new one is kept out of the index
+        // while flushing, so it stays in memory
+        _ = self.remove_entry(&key);
+        let flushed = self.flush_to_high_watermark().await;
+        self.insert(
+            key,
+            IndexEntry {
+                entry,
+                compressed: compress,
+                byte_len,
+                created_at: Some(Instant::now()),
+            },
+        );
+        flushed?;
+        Ok(location)
+    }
+
+    /// Move entries out of memory until memory usage drops to the configured
+    /// [flush high watermark](crate::cache_builder::CacheBuilderWithStrategy::flush_high_watermark).
+    ///
+    /// Entries are visited in the configured [FlushOrder]. Stops early if the
+    /// strategy can't move entries out of memory.
+    async fn flush_to_high_watermark(&mut self) -> Result<()>
+    where
+        S::CacheEntry: Sync,
+    {
+        let Some(high_watermark) = self.options.flush_high_watermark else {
+            return Ok(());
+        };
+        let Some(capacity) = self.strategy.tier_capacity(Tier::Memory) else {
+            return Ok(());
+        };
+        let target = (capacity.total() as f64 * high_watermark) as usize;
+        let mut used = capacity.used();
+        if used <= target {
+            return Ok(());
+        }
+
+        let strategy = &mut self.strategy;
+        let mut entries: Vec<_> = self
+            .data
+            .iter_mut()
+            .filter(|(_, entry)| strategy.entry_location(&entry.entry).is_in_memory())
+            .collect();
+        if self.options.flush_order == FlushOrder::OldestFirst {
+            // Entries of unknown age sort first
+            entries.sort_by_key(|(_, entry)| entry.created_at);
+        }
+
+        for (key, entry) in entries {
+            if used <= target {
+                break;
+            }
+            let Some(demoted) = strategy.demote(key, &entry.entry).await? else {
+                break;
+            };
+            entry.entry = demoted;
+            used = strategy
+                .tier_capacity(Tier::Memory)
+                .map_or(0, |capacity| capacity.used());
+        }
+        Ok(())
+    }
+
+    /// Compress a value as requested by `compression`.
+    async fn compress<'a>(
+        &self,
+        value: Cow<'a, [u8]>,
+        compression: Compression,
+    ) -> Result<Cow<'a, [u8]>> {
+        match compression {
+            Compression::None => Ok(value),
+            Compression::Default => self.compressor.compress(value).await,
+            Compression::Level(level) => self.compressor.compress_with_level(value, level).await,
+        }
+    }
+
+    /// Check whether putting an entry would exceed the cache-level limits.
+    fn check_limits(&self, key: &K, byte_len: usize) -> Result<()> {
+        // Check if the maximum number of keys has been reached
+        if let Some(max_keys) = self.options.max_keys {
+            if !self.data.contains_key(key) && self.data.len() >= max_keys {
+                return Err(crate::Error::LimitExceeded {
+                    limit_kind: LIMIT_KIND_KEY.into(),
+                });
+            }
+        }
+
+        if !self.options.enforce_uncompressed_byte_limit {
+            return Ok(());
+        }
+        let Some(capacity) = self.strategy.get_cache_capacity() else {
+            return Ok(());
+        };
+
+        // An existing entry under the same key is replaced
+        let (replaced_byte_len, entry_count) = match self.data.get(key) {
+            Some(entry) => (entry.byte_len, self.data.len()),
+            None => (0, self.data.len() + 1),
+        };
+        let byte_count = self
+            .uncompressed_byte_count
+            .saturating_sub(replaced_byte_len)
+            .saturating_add(byte_len)
+            .saturating_add(entry_count.saturating_mul(self.strategy.entry_overhead()));
+        if byte_count > capacity.total() {
+            return Err(crate::Error::LimitExceeded {
+                limit_kind: LIMIT_KIND_BYTE_UNCOMPRESSED.into(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Get an entry from the cache, returning `None` if it doesn't exist.
+    async fn get_existing(&self, key: &K) -> Result<Option<Cow<'_, [u8]>>> {
+        let Some(entry) = self.data.get(key) else {
+            return Ok(None);
+        };
+        let value = self.strategy.get(&entry.entry).await?;
+        Ok(Some(self.decompress(entry.compressed, value).await?))
+    }
+
+    /// Undo applied puts in reverse order, restoring previous values.
+    /// Errors are ignored, since this only runs while handling another error.
+    async fn rollback(&mut self, applied: Vec<(K, Option<PreviousValue>)>)
+    where
+        S::CacheEntry: Sync,
+    {
+        for (key, previous) in applied.into_iter().rev() {
+            if let Ok(entry) = self.remove_entry(&key) {
+                _ = self.strategy.delete(entry.entry).await;
+            }
+            if let Some(PreviousValue { value, compressed }) = previous {
+                _ = self
+                    .put_entry(key, None, value.into(), compressed.into())
+                    .await;
+            }
+        }
+    }
+
+    /// Take an entry's value from the strategy, decompressing it if needed.
+    async fn take_entry(&mut self, entry: IndexEntry<S::CacheEntry>) -> Result<Vec<u8>> {
+        let value = self.strategy.take(entry.entry).await?;
+        Ok(self
+            .decompress(entry.compressed, value.into())
+            .await?
+            .into_owned())
+    }
+
+    /// Decompress an entry's value if it was compressed.
+    async fn decompress<'a>(
+        &self,
+        compressed: bool,
+        value: Cow<'a, [u8]>,
+    ) -> Result<Cow<'a, [u8]>> {
+        decompress_with(&self.compressor, compressed, value).await
+    }
+
     #[cfg(test)]
     pub(crate) fn strategy(&self) -> &S {
         &self.strategy
     }
+
+    #[cfg(test)]
+    pub(crate) fn strategy_mut(&mut self) -> &mut S {
+        &mut self.strategy
+    }
+
+    #[cfg(test)]
+    pub(crate) fn index_capacity(&self) -> usize {
+        self.data.capacity()
+    }
+}
+
+impl<K, S, C> Cache<K, S, C>
+where
+    K: CacheKey + Eq + Hash,
+    S: CacheStrategy,
+    C: CompressionStrategy + Sync + Send,
+{
+    /// Insert an entry into the index, keeping the uncompressed byte count in sync.
+    fn insert(&mut self, key: K, entry: IndexEntry<S::CacheEntry>) {
+        self.uncompressed_byte_count = self.uncompressed_byte_count.saturating_add(entry.byte_len);
+        if let Some(old_entry) = self.data.insert(key, entry) {
+            self.uncompressed_byte_count = self
+                .uncompressed_byte_count
+                .saturating_sub(old_entry.byte_len);
+        }
+    }
+
+    /// Remove an entry from the index, keeping the uncompressed byte count in sync.
+    fn remove_entry(&mut self, key: &K) -> Result<IndexEntry<S::CacheEntry>> {
+        let entry = self.data.remove(key).ok_or(crate::Error::KeyNotFound)?;
+        self.uncompressed_byte_count = self.uncompressed_byte_count.saturating_sub(entry.byte_len);
+        Ok(entry)
+    }
 }
 
 impl<K, S, C> Cache<K, S, C>
@@ -98,21 +1517,158 @@ where
     /// Useful after a crash or unplanned restart. It's good practice to call this
     /// method on startup, but it depends on your specific use case.
     ///
+    /// Recover before putting new entries. If a key was already put, the indexed
+    /// entry is kept and its recovered counterpart isn't counted. A stale copy
+    /// stored elsewhere, e.g. on disk while the new entry is in memory, is deleted.
+    ///
     /// ## Disclaimer
     /// This is a best-effort operation, full recovery is not guaranteed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub async fn recover<F>(&mut self, key_from_str: F) -> Result<usize>
     where
         F: Fn(&str) -> Option<K> + Send,
     {
         // Recover cache using the strategy
         let entries = self.strategy.recover(key_from_str).await?;
-        let recovered_item_count = entries.len();
+        self.index_recovered(entries).await
+    }
 
-        // Insert recovered entries into the cache
-        for (key, entry) in entries {
-            self.data.insert(key, entry);
+    /// Recover the cache from a previous state, reporting what happened to the stored entries.
+    ///
+    /// Unlike [`recover`](Self::recover), stored entries that can't be read are
+    /// skipped instead of failing the recovery. The [RecoveryReport] counts them,
+    /// along with the recovered entries and the ones moved to `lost+found`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn recover_with_report<F>(&mut self, key_from_str: F) -> Result<RecoveryReport>
+    where
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        // Recover cache using the strategy
+        let (entries, mut report) = self.strategy.recover_with_report(key_from_str).await?;
+        report.recovered = self.index_recovered(entries).await?;
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            recovered = report.recovered,
+            relocated_to_lost_found = report.relocated_to_lost_found,
+            read_errors = report.read_errors,
+            "cache recovered"
+        );
+
+        Ok(report)
+    }
+
+    /// Recover the cache from a previous state, without reading the stored entries.
+    /// Returns the number of recovered items.
+    ///
+    /// Unlike [`recover`](Self::recover), stored entries are only listed, and their
+    /// sizes are taken from the file system. This turns recovering a large cache
+    /// from reading all of its data into a quick directory walk. Entries are read
+    /// when they are first accessed, so unreadable ones only show up then, or when
+    /// [verifying](Self::verify) the cache.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn recover_fast<F>(&mut self, key_from_str: F) -> Result<usize>
+    where
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        // Recover cache using the strategy
+        let entries = self.strategy.recover_fast(key_from_str).await?;
+        self.index_recovered(entries).await
+    }
+
+    /// Recover entries from `lost+found` that a previous recovery couldn't identify.
+    /// Returns the number of recovered items.
+    ///
+    /// Useful after fixing `key_from_str`: entries whose key can now be recovered
+    /// are moved back into the cache and indexed, the rest stay in `lost+found`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn recover_lost_found<F>(&mut self, key_from_str: F) -> Result<usize>
+    where
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        // Recover entries using the strategy
+        let entries = self.strategy.recover_lost_found(key_from_str).await?;
+        self.index_recovered(entries).await
+    }
+
+    /// Recover the cache from a previous state, only considering stored entries
+    /// whose name passes `should_consider`.
+    /// Returns the number of recovered items.
+    ///
+    /// Unlike [`recover`](Self::recover), entries failing the predicate are left
+    /// untouched instead of being moved to `lost+found`. This is useful when the
+    /// cache directory is shared with other tools.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn recover_filtered<F, P>(
+        &mut self,
+        key_from_str: F,
+        should_consider: P,
+    ) -> Result<usize>
+    where
+        F: Fn(&str) -> Option<K> + Send,
+        P: Fn(&str) -> bool + Send,
+    {
+        // Recover cache using the strategy
+        let entries = self
+            .strategy
+            .recover_filtered(key_from_str, should_consider)
+            .await?;
+        self.index_recovered(entries).await
+    }
+
+    /// Rebuild the index of stored entries from the strategy's storage, e.g. after
+    /// files were added or removed by external tools.
+    /// Returns the number of recovered items.
+    ///
+    /// Entries held on disk are dropped from the index without touching their
+    /// files, then the cache is [recovered](Self::recover), which recomputes the
+    /// strategy's counters. Entries held in memory are kept.
+    ///
+    /// ## Disclaimer
+    /// Like any recovered entry, resynced entries have no known age, and entries
+    /// stored without a header are assumed to be compressed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn resync<F>(&mut self, key_from_str: F) -> Result<usize>
+    where
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        let data = std::mem::take(&mut self.data);
+        self.uncompressed_byte_count = 0;
+        for (key, entry) in data {
+            match self.strategy.entry_location(&entry.entry) {
+                EntryLocation::Disk => self.strategy.forget(entry.entry),
+                _ => self.insert(key, entry),
+            }
         }
 
+        self.recover(key_from_str).await
+    }
+
+    /// Index recovered entries, returning how many were added.
+    ///
+    /// Keys that are already indexed keep their entry. The recovered entry is
+    /// forgotten if it refers to the same stored data, or deleted otherwise.
+    async fn index_recovered(&mut self, entries: Vec<(K, S::CacheEntry)>) -> Result<usize> {
+        let mut recovered_item_count = 0;
+        for (key, entry) in entries {
+            if let Some(existing) = self.data.get(&key) {
+                let existing_location = self.strategy.entry_location(&existing.entry);
+                let location = self.strategy.entry_location(&entry);
+                if existing_location == location
+                    || (existing_location.is_on_disk() && location.is_on_disk())
+                {
+                    self.strategy.forget(entry);
+                } else {
+                    self.strategy.delete(entry).await?;
+                }
+                continue;
+            }
+
+            let stored_len = self.strategy.stored_byte_len(&entry).unwrap_or_default();
+            let header = self.strategy.entry_header(&entry);
+            self.insert(key, IndexEntry::recovered(entry, stored_len, header));
+            recovered_item_count += 1;
+        }
         Ok(recovered_item_count)
     }
 }
@@ -120,37 +1676,169 @@ where
 impl<K, S, C> Cache<K, S, C>
 where
     K: CacheKey + Eq + Hash + ToOwned<Owned = K> + Sync + Send,
-    S: FlushableStrategy,
+    S: FlushableStrategy + Send,
+    S::CacheEntry: Sync + Send,
     C: CompressionStrategy + Sync + Send,
 {
     /// Flush entries to an underlying non-volatile storage.
     /// Returns the number of flushed items.
+    ///
+    /// Flushed entries are moved, see [flush_with_mode](Self::flush_with_mode).
     pub async fn flush(&mut self) -> Result<usize> {
+        self.flush_with_mode(FlushMode::Move).await
+    }
+
+    /// Flush entries to an underlying non-volatile storage.
+    /// Returns the number of flushed items.
+    ///
+    /// With [FlushMode::Copy], entries stay in memory and are also written to disk,
+    /// so reads stay fast while the data survives a restart. A later move only
+    /// releases the memory of such entries, since their copy is already on disk.
+    ///
+    /// Entries are flushed one at a time, in the configured [FlushOrder]. If an error
+    /// occurs, e.g. because the disk tier is full, the entries flushed so far stay flushed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn flush_with_mode(&mut self, mode: FlushMode) -> Result<usize> {
         let mut flushed_item_count = 0;
-        let mut keys_to_remove = Vec::<K>::new();
-        let mut entries_to_insert = Vec::new();
+        for key in self.keys_in_flush_order(|_| true) {
+            if self.flush_entry(key, mode).await? {
+                flushed_item_count += 1;
+            }
+        }
+        Ok(flushed_item_count)
+    }
 
-        // Flush all entries using the strategy
-        for (key, entry) in self.data.iter() {
-            let Some(new_entry) = self.strategy.flush(key, entry).await? else {
-                continue;
+    /// Flush a single entry to an underlying non-volatile storage.
+    /// Returns `true` if the entry was flushed, or `false` if it doesn't exist
+    /// or doesn't need to be flushed.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(key = %key.to_key()))
+    )]
+    pub async fn flush_key(&mut self, key: K) -> Result<bool> {
+        self.flush_entry(key, FlushMode::Move).await
+    }
+
+    /// Flush entries held in memory until memory usage drops to `high_watermark`,
+    /// a fraction of the memory byte limit.
+    /// Returns the number of flushed items.
+    /// Entries are visited in the configured [FlushOrder].
+    ///
+    /// Call this after putting entries to keep memory bounded without rejecting puts.
+    /// Nothing is flushed if the strategy has no memory byte limit or usage is
+    /// already at or below the watermark.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn flush_to_watermark(&mut self, high_watermark: f64) -> Result<usize> {
+        let Some(capacity) = self.strategy.tier_capacity(Tier::Memory) else {
+            return Ok(0);
+        };
+        let target = (capacity.total() as f64 * high_watermark) as usize;
+        let mut used = capacity.used();
+        if used <= target {
+            return Ok(0);
+        }
+
+        let keys = self
+            .keys_in_flush_order(|entry| self.strategy.entry_location(&entry.entry).is_in_memory());
+
+        let mut flushed_item_count = 0;
+        for key in keys {
+            if used <= target {
+                break;
+            }
+            if self.flush_key(key).await? {
+                flushed_item_count += 1;
+            }
+            used = self
+                .strategy
+                .tier_capacity(Tier::Memory)
+                .map_or(0, |capacity| capacity.used());
+        }
+
+        Ok(flushed_item_count)
+    }
+
+    /// Flush a single entry, replacing it with the flushed one.
+    async fn flush_entry(&mut self, key: K, mode: FlushMode) -> Result<bool> {
+        if mode == FlushMode::Copy {
+            // The entry is updated in place, keeping its memory
+            let Some(entry) = self.data.get_mut(&key) else {
+                return Ok(false);
             };
-            keys_to_remove.push(key.to_owned());
-            entries_to_insert.push((key.to_owned(), new_entry));
-            flushed_item_count += 1;
+            return self.strategy.flush_copy(&key, &mut entry.entry).await;
         }
 
-        // Remove flushed entries from the cache
-        for key in keys_to_remove {
-            let entry = self.data.remove(&key).ok_or(crate::Error::KeyNotFound)?;
-            self.strategy.delete(entry).await?;
+        let Some(entry) = self.data.get(&key) else {
+            return Ok(false);
+        };
+        let Some(new_entry) = self.strategy.flush(&key, &entry.entry).await? else {
+            return Ok(false);
+        };
+        let new_entry = entry.with_entry(new_entry);
+
+        // Replace the flushed entry
+        if let Some(old_entry) = self.data.insert(key, new_entry) {
+            self.strategy.release_flushed(old_entry.entry).await?;
         }
 
-        // Insert moved entries into the cache
-        for (key, entry) in entries_to_insert {
-            self.data.insert(key, entry);
+        Ok(true)
+    }
+
+    /// Get the keys of the entries matching `filter`, in the configured [FlushOrder].
+    fn keys_in_flush_order<F>(&self, filter: F) -> Vec<K>
+    where
+        F: Fn(&IndexEntry<S::CacheEntry>) -> bool,
+    {
+        let mut entries: Vec<_> = self
+            .data
+            .iter()
+            .filter(|(_, entry)| filter(entry))
+            .collect();
+        if self.options.flush_order == FlushOrder::OldestFirst {
+            // Entries of unknown age sort first
+            entries.sort_by_key(|(_, entry)| entry.created_at);
         }
+        entries.into_iter().map(|(key, _)| key.to_owned()).collect()
+    }
+}
 
-        Ok(flushed_item_count)
+/// Decompress an entry's value with `compressor` if it was compressed.
+async fn decompress_with<'a, C>(
+    compressor: &Option<C>,
+    compressed: bool,
+    value: Cow<'a, [u8]>,
+) -> Result<Cow<'a, [u8]>>
+where
+    C: CompressionStrategy + Sync + Send,
+{
+    match compressed {
+        // Compressed data is never empty, so an empty value was stored as-is,
+        // e.g. a recovered empty file.
+        true if value.is_empty() => Ok(value),
+        true => compressor.decompress(value).await,
+        false => Ok(value),
+    }
+}
+
+/// Record the byte length of a value on the current span.
+#[inline]
+fn trace_byte_len(byte_len: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("byte_len", byte_len);
+    #[cfg(not(feature = "tracing"))]
+    let _ = byte_len;
+}
+
+/// Emit an event for cache misses and exceeded limits.
+#[inline]
+fn trace_error(err: crate::Error) -> crate::Error {
+    #[cfg(feature = "tracing")]
+    match &err {
+        crate::Error::KeyNotFound => tracing::debug!("cache miss"),
+        crate::Error::LimitExceeded { limit_kind } => {
+            tracing::warn!(limit_kind = %limit_kind, "cache limit exceeded")
+        }
+        _ => {}
     }
+    err
 }
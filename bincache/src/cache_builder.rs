@@ -1,6 +1,13 @@
-use std::hash::Hash;
+use std::{borrow::Cow, hash::Hash, path::Path};
 
-use crate::{noop::Noop, Cache, CacheKey, CacheStrategy, CompressionStrategy, Result};
+#[cfg(feature = "comp_zstd")]
+use crate::compression::Zstd;
+use crate::{
+    cache::CacheOptions,
+    noop::Noop,
+    strategies::{Disk, Hybrid, Memory},
+    Cache, CacheKey, CacheStrategy, CompressionStrategy, FlushOrder, KeyEncoding, Result,
+};
 
 /// A builder for creating a new [Cache].
 ///
@@ -23,6 +30,7 @@ pub struct CacheBuilder;
 
 pub struct CacheBuilderWithStrategy<S> {
     strategy: S,
+    options: CacheOptions,
 }
 
 impl<S> Default for CacheBuilderWithStrategy<S>
@@ -32,12 +40,14 @@ where
     fn default() -> Self {
         CacheBuilderWithStrategy {
             strategy: S::default(),
+            options: CacheOptions::default(),
         }
     }
 }
 
 pub struct CacheBuilderWithCompression<C> {
     compressor: C,
+    options: CacheOptions,
 }
 
 impl<C> Default for CacheBuilderWithCompression<C>
@@ -47,6 +57,7 @@ where
     fn default() -> Self {
         CacheBuilderWithCompression {
             compressor: C::default(),
+            options: CacheOptions::default(),
         }
     }
 }
@@ -54,6 +65,7 @@ where
 pub struct CacheBuilderWithCompressionAndStrategy<S, C> {
     strategy: S,
     compressor: C,
+    options: CacheOptions,
 }
 
 impl<S, C> Default for CacheBuilderWithCompressionAndStrategy<S, C>
@@ -65,17 +77,85 @@ where
         CacheBuilderWithCompressionAndStrategy {
             strategy: S::default(),
             compressor: C::default(),
+            options: CacheOptions::default(),
         }
     }
 }
 
+/// Generate the setters for [CacheOptions] on the builders.
+///
+/// `prefer_smaller` only has an effect with a compressor, so it's only generated
+/// for the builders with compression.
+macro_rules! cache_option_setters {
+    () => {
+        /// Enforce the strategy's byte limit against the uncompressed size of entries
+        ///
+        /// By default the limit applies to the compressed size, since values are
+        /// compressed before they are stored.
+        pub fn enforce_uncompressed_byte_limit(mut self, enforce: bool) -> Self {
+            self.options.enforce_uncompressed_byte_limit = enforce;
+            self
+        }
+
+        /// Set the maximum number of keys the cache tracks
+        ///
+        /// This is enforced by the cache itself, independent of any strategy limits.
+        pub fn with_max_keys(mut self, max_keys: usize) -> Self {
+            self.options.max_keys = Some(max_keys);
+            self
+        }
+
+        /// Reserve room in the cache index for `capacity` keys up front
+        ///
+        /// This avoids rehashing the index while a large cache warms up.
+        pub fn with_capacity_hint(mut self, capacity: usize) -> Self {
+            self.options.capacity_hint = capacity;
+            self
+        }
+
+        /// Set the order in which entries are flushed
+        ///
+        /// See [FlushOrder] for the available orders.
+        pub fn with_flush_order(mut self, flush_order: FlushOrder) -> Self {
+            self.options.flush_order = flush_order;
+            self
+        }
+
+        /// Flush entries held in memory after each put, until memory usage drops to
+        /// `fraction` of the memory byte limit
+        ///
+        /// This keeps memory bounded without a background task and without rejecting puts.
+        /// Only strategies that can move entries out of memory, like [Hybrid], flush entries.
+        /// Building the cache fails if `fraction` isn't between 0 and 1.
+        pub fn flush_high_watermark(mut self, fraction: f64) -> Self {
+            self.options.flush_high_watermark = Some(fraction);
+            self
+        }
+    };
+    (compression) => {
+        cache_option_setters!();
+
+        /// Store values uncompressed if compressing them doesn't make them smaller
+        ///
+        /// This prevents already dense data from growing when it's compressed, at the cost
+        /// of keeping the original value around until compression is done.
+        pub fn prefer_smaller(mut self, prefer_smaller: bool) -> Self {
+            self.options.prefer_smaller = prefer_smaller;
+            self
+        }
+    };
+}
+
 impl CacheBuilder {
     /// Add a strategy to the cache
     pub fn with_strategy<S>(self, strategy: S) -> CacheBuilderWithStrategy<S>
     where
         S: CacheStrategy,
     {
-        CacheBuilderWithStrategy { strategy }
+        CacheBuilderWithStrategy {
+            strategy,
+            options: CacheOptions::default(),
+        }
     }
 
     /// Add a compression algorithm to the cache
@@ -83,7 +163,10 @@ impl CacheBuilder {
     where
         C: CompressionStrategy,
     {
-        CacheBuilderWithCompression { compressor }
+        CacheBuilderWithCompression {
+            compressor,
+            options: CacheOptions::default(),
+        }
     }
 }
 
@@ -94,9 +177,12 @@ impl<C> CacheBuilderWithCompression<C> {
             CacheBuilderWithCompressionAndStrategy {
                 strategy,
                 compressor: self.compressor,
+                options: self.options,
             }
         }
     }
+
+    cache_option_setters!(compression);
 }
 
 impl<S> CacheBuilderWithStrategy<S>
@@ -111,6 +197,17 @@ where
         CacheBuilderWithCompressionAndStrategy {
             strategy: self.strategy,
             compressor,
+            options: self.options,
+        }
+    }
+
+    cache_option_setters!();
+
+    /// Configure the strategy in place
+    pub fn map_strategy(self, f: impl FnOnce(S) -> S) -> Self {
+        CacheBuilderWithStrategy {
+            strategy: f(self.strategy),
+            ..self
         }
     }
 
@@ -119,27 +216,147 @@ where
     where
         K: CacheKey + Eq + Hash + Sync + Send,
     {
-        Cache::new(self.strategy, None).await
+        Cache::with_options(self.strategy, None, self.options).await
     }
 }
 
+/// Forward strategy setters to the builders using that strategy,
+/// so aliased builders like [HybridCacheBuilder](crate::HybridCacheBuilder) can be configured.
+macro_rules! forward_strategy_setters {
+    ($strategy:ty { $($(#[$meta:meta])* fn $name:ident $(<$lt:lifetime>)? ($arg:ident: $arg_ty:ty);)* }) => {
+        impl CacheBuilderWithStrategy<$strategy> {
+            $(
+                $(#[$meta])*
+                pub fn $name $(<$lt>)? (self, $arg: $arg_ty) -> Self {
+                    self.map_strategy(|strategy| strategy.$name($arg))
+                }
+            )*
+        }
+
+        impl<C: CompressionStrategy> CacheBuilderWithCompressionAndStrategy<$strategy, C> {
+            $(
+                $(#[$meta])*
+                pub fn $name $(<$lt>)? (self, $arg: $arg_ty) -> Self {
+                    self.map_strategy(|strategy| strategy.$name($arg))
+                }
+            )*
+        }
+    };
+}
+
+forward_strategy_setters!(Disk {
+    /// Set the directory where entries are stored
+    fn with_cache_dir<'a>(cache_dir: impl Into<Cow<'a, Path>>);
+    /// Set the maximum number of bytes that can be stored
+    fn with_byte_limit(byte_limit: usize);
+    /// Set the maximum number of entries that can be stored
+    fn with_entry_limit(entry_limit: usize);
+    /// Set the permissions of the cache directory, e.g. `0o700`
+    fn with_dir_mode(mode: u32);
+    /// Set the permissions of created entry files, e.g. `0o600`
+    fn with_file_mode(mode: u32);
+    /// Set how keys are encoded as file names
+    fn with_key_encoding(key_encoding: KeyEncoding);
+    /// Move the files of taken and deleted entries into a directory instead of removing them
+    fn archive_taken_to<'a>(archive_dir: impl Into<Cow<'a, Path>>);
+    /// Write entry files to a temporary directory first, then move them into place
+    fn with_temp_dir<'a>(temp_dir: impl Into<Cow<'a, Path>>);
+    /// Check that entries read from disk have the size they were stored with
+    fn with_strict_reads(strict_reads: bool);
+    /// Set the number of bytes each entry occupies on disk in addition to its data
+    fn with_entry_overhead(entry_overhead: usize);
+    /// Keep the data of the `entry_count` most recently read entries in memory
+    fn with_read_buffer(entry_count: usize);
+});
+
+forward_strategy_setters!(Hybrid {
+    /// Set the directory where entries are stored on disk
+    fn with_cache_dir<'a>(cache_dir: impl Into<Cow<'a, Path>>);
+    /// Set the maximum number of bytes that can be stored in memory
+    fn with_memory_byte_limit(byte_limit: usize);
+    /// Set the maximum number of entries that can be stored in memory
+    fn with_memory_entry_limit(entry_limit: usize);
+    /// Set the maximum number of bytes that can be stored on disk
+    fn with_disk_byte_limit(byte_limit: usize);
+    /// Set the maximum number of entries that can be stored on disk
+    fn with_disk_entry_limit(entry_limit: usize);
+    /// Set the permissions of the cache directory, e.g. `0o700`
+    fn with_dir_mode(mode: u32);
+    /// Set the permissions of created entry files, e.g. `0o600`
+    fn with_file_mode(mode: u32);
+    /// Set how keys are encoded as file names
+    fn with_key_encoding(key_encoding: KeyEncoding);
+    /// Move the files of taken and deleted entries into a directory instead of removing them
+    fn archive_taken_to<'a>(archive_dir: impl Into<Cow<'a, Path>>);
+    /// Write entry files to a temporary directory first, then move them into place
+    fn with_temp_dir<'a>(temp_dir: impl Into<Cow<'a, Path>>);
+    /// Compress entries written to disk, keeping entries in memory uncompressed
+    ///
+    /// Combined with a cache-level compressor, disk entries are compressed twice.
+    fn with_disk_compression(compressor: impl CompressionStrategy + Send + Sync + 'static);
+});
+
+forward_strategy_setters!(Memory {
+    /// Move values that exceed the byte limit into a directory instead of rejecting them
+    fn with_spill_dir<'a>(spill_dir: impl Into<Cow<'a, Path>>);
+    /// Limit the number of bytes that can be spilled to disk
+    fn with_spill_byte_limit(spill_byte_limit: usize);
+});
+
 impl<S, C> CacheBuilderWithCompressionAndStrategy<S, C>
 where
     S: CacheStrategy + Send,
     C: CompressionStrategy,
 {
+    cache_option_setters!(compression);
+
+    /// Configure the strategy in place
+    pub fn map_strategy(self, f: impl FnOnce(S) -> S) -> Self {
+        CacheBuilderWithCompressionAndStrategy {
+            strategy: f(self.strategy),
+            ..self
+        }
+    }
+
+    /// Build the cache
     pub async fn build<K>(self) -> Result<Cache<K, S, C>>
     where
         K: CacheKey + Eq + Hash + Sync + Send,
         C: CompressionStrategy + Sync + Send,
     {
-        Cache::new(self.strategy, Some(self.compressor)).await
+        Cache::with_options(self.strategy, Some(self.compressor), self.options).await
+    }
+}
+
+#[cfg(feature = "comp_zstd")]
+impl CacheBuilderWithCompression<Zstd> {
+    /// Load a pretrained compression dictionary from a file
+    ///
+    /// See [Zstd::with_dictionary_file].
+    pub fn with_compression_dictionary(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        self.compressor = self.compressor.with_dictionary_file(path)?;
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "comp_zstd")]
+impl<S> CacheBuilderWithCompressionAndStrategy<S, Zstd> {
+    /// Load a pretrained compression dictionary from a file
+    ///
+    /// See [Zstd::with_dictionary_file].
+    pub fn with_compression_dictionary(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        self.compressor = self.compressor.with_dictionary_file(path)?;
+        Ok(self)
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::default_constructed_unit_structs)]
 mod tests {
-    use crate::{async_test, noop::Noop};
+    use crate::{
+        async_test, noop::Noop, utils::test::TempDir, DiskCacheBuilder, Error, HybridCacheBuilder,
+        MemoryCacheBuilder, MemoryStrategy,
+    };
 
     use super::*;
 
@@ -157,5 +374,124 @@ mod tests {
             let mut cache = CacheBuilder::default().with_strategy(Noop).build().await.unwrap();
             cache.put("test".to_string(), vec![]).await.unwrap();
         }
+
+        async fn test_hybrid_builder() {
+            let temp_dir = TempDir::new();
+            let mut cache = HybridCacheBuilder::default()
+                .with_cache_dir(temp_dir.as_ref())
+                .with_memory_entry_limit(1)
+                .with_disk_entry_limit(1)
+                .build()
+                .await
+                .unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            assert!(temp_dir.as_ref().join("bar").is_file());
+            assert!(cache.put("baz", b"baz".to_vec()).await.is_err());
+        }
+
+        async fn test_max_keys() {
+            let mut cache = CacheBuilder
+                .with_strategy(MemoryStrategy::default())
+                .with_max_keys(2)
+                .build()
+                .await
+                .unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+            cache.put("foo", b"baz".to_vec()).await.unwrap();
+            assert!(matches!(
+                cache.put("baz", b"baz".to_vec()).await,
+                Err(Error::LimitExceeded { .. })
+            ));
+
+            cache.delete("foo").await.unwrap();
+            cache.put("baz", b"baz".to_vec()).await.unwrap();
+        }
+
+        async fn test_hybrid_builder_with_compression() {
+            let temp_dir = TempDir::new();
+            let mut cache = HybridCacheBuilder::default()
+                .with_compression(Noop)
+                .with_cache_dir(temp_dir.as_ref())
+                .with_memory_entry_limit(1)
+                .build()
+                .await
+                .unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            assert!(temp_dir.as_ref().join("bar").is_file());
+        }
+
+        async fn test_map_strategy() {
+            let mut cache = MemoryCacheBuilder::default()
+                .map_strategy(|_| MemoryStrategy::new(None, Some(1)))
+                .build()
+                .await
+                .unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            assert!(cache.put("bar", b"bar".to_vec()).await.is_err());
+        }
+
+        async fn test_disk_builder() {
+            let temp_dir = TempDir::new();
+            let mut cache = DiskCacheBuilder::default()
+                .with_cache_dir(temp_dir.as_ref())
+                .with_byte_limit(6)
+                .with_entry_limit(2)
+                .build()
+                .await
+                .unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            assert!(temp_dir.as_ref().join("foo").is_file());
+            assert_eq!(cache.capacity().unwrap().total(), 6);
+        }
+
+        async fn test_capacity_hint() {
+            let cache = CacheBuilder
+                .with_strategy(MemoryStrategy::default())
+                .with_capacity_hint(1000)
+                .build::<&str>()
+                .await
+                .unwrap();
+
+            assert!(cache.index_capacity() >= 1000);
+        }
+
+
+        async fn test_forwarded_strategy_setters() {
+            let temp_dir = TempDir::new();
+            let mut cache = MemoryCacheBuilder::default()
+                .map_strategy(|_| MemoryStrategy::new(Some(4), None))
+                .with_spill_dir(temp_dir.as_ref())
+                .with_spill_byte_limit(8)
+                .build()
+                .await
+                .unwrap();
+
+            let location = cache.put_located("foo", b"foobar".to_vec()).await.unwrap();
+            assert_eq!(location, crate::EntryLocation::Spilled);
+            assert!(cache.put("bar", b"barbaz".to_vec()).await.is_err());
+
+            let temp_dir = TempDir::new();
+            let mut cache = DiskCacheBuilder::default()
+                .with_compression(Noop)
+                .with_cache_dir(temp_dir.as_ref())
+                .with_entry_overhead(10)
+                .with_byte_limit(16)
+                .build()
+                .await
+                .unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            assert!(cache.put("bar", b"bar".to_vec()).await.is_err());
+        }
     }
 }
@@ -22,8 +22,23 @@ impl CacheCapacity {
         self.used_bytes
     }
 
+    /// Get the remaining cache capacity in bytes.
+    pub fn remaining(&self) -> usize {
+        self.total_bytes.saturating_sub(self.used_bytes)
+    }
+
+    /// Check whether the cache is full.
+    pub fn is_full(&self) -> bool {
+        self.used_bytes >= self.total_bytes
+    }
+
     /// Get the cache utilization as a value between 0 and 1.
+    ///
+    /// Returns 0 if the total capacity is zero.
     pub fn utilization(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
         self.used_bytes as f64 / self.total_bytes as f64
     }
 
@@ -32,3 +47,26 @@ impl CacheCapacity {
         self.utilization() * 100.00
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CacheCapacity;
+
+    #[test]
+    fn test_utilization() {
+        let capacity = CacheCapacity::new(200, 50);
+        assert_eq!(capacity.utilization(), 0.25);
+        assert_eq!(capacity.utilization_percentage(), 25.0);
+        assert_eq!(capacity.remaining(), 150);
+        assert!(!capacity.is_full());
+    }
+
+    #[test]
+    fn test_zero_total() {
+        let capacity = CacheCapacity::new(0, 0);
+        assert_eq!(capacity.utilization(), 0.0);
+        assert_eq!(capacity.utilization_percentage(), 0.0);
+        assert_eq!(capacity.remaining(), 0);
+        assert!(capacity.is_full());
+    }
+}
@@ -0,0 +1,26 @@
+/// Summary of a compaction of a [Cache](crate::Cache).
+///
+/// See [Cache::compact](crate::Cache::compact).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CompactReport {
+    pub(crate) removed_empty_dirs: usize,
+    pub(crate) removed_lost_found: usize,
+    pub(crate) removed_temp_files: usize,
+}
+
+impl CompactReport {
+    /// Get the number of empty directories removed from the cache directory.
+    pub fn removed_empty_dirs(&self) -> usize {
+        self.removed_empty_dirs
+    }
+
+    /// Get the number of stale files removed from `lost+found`.
+    pub fn removed_lost_found(&self) -> usize {
+        self.removed_lost_found
+    }
+
+    /// Get the number of stale temporary files removed, e.g. left behind by interrupted writes.
+    pub fn removed_temp_files(&self) -> usize {
+        self.removed_temp_files
+    }
+}
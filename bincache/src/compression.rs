@@ -5,6 +5,22 @@ pub use compression_level::CompressionLevel;
 /// A no-op compression strategy.
 pub const NO_COMPRESSION: Option<crate::noop::Noop> = None;
 
+/// Map an I/O error raised while compressing to [Error::CompressionError](crate::Error::CompressionError).
+#[cfg(any(feature = "comp_zstd", feature = "comp_brotli", feature = "comp_gzip"))]
+pub(crate) fn compression_error(
+    algorithm: &'static str,
+) -> impl Fn(std::io::Error) -> crate::Error {
+    move |source| crate::Error::CompressionError { algorithm, source }
+}
+
+/// Map an I/O error raised while decompressing to [Error::DecompressionError](crate::Error::DecompressionError).
+#[cfg(any(feature = "comp_zstd", feature = "comp_brotli", feature = "comp_gzip"))]
+pub(crate) fn decompression_error(
+    algorithm: &'static str,
+) -> impl Fn(std::io::Error) -> crate::Error {
+    move |source| crate::Error::DecompressionError { algorithm, source }
+}
+
 #[cfg(feature = "comp_zstd")]
 mod zstd_compressor;
 #[cfg(feature = "comp_zstd")]
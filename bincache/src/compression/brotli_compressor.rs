@@ -1,4 +1,4 @@
-use super::compression_level::CompressionLevel;
+use super::{compression_error, compression_level::CompressionLevel, decompression_error};
 use crate::traits::CompressionStrategy;
 use crate::Result;
 use async_trait::async_trait;
@@ -25,19 +25,37 @@ impl Default for Brotli {
     }
 }
 
+const ALGORITHM: &str = "brotli";
+/// The level used for [CompressionLevel::Balanced].
+const BALANCED_LEVEL: i32 = 6;
+
 #[async_trait]
 impl CompressionStrategy for Brotli {
     async fn compress<'a>(&self, data: Cow<'a, [u8]>) -> Result<Cow<'a, [u8]>> {
+        self.compress_with_level(data, self.level).await
+    }
+
+    async fn compress_with_level<'a>(
+        &self,
+        data: Cow<'a, [u8]>,
+        level: CompressionLevel,
+    ) -> Result<Cow<'a, [u8]>> {
         #[cfg(feature = "rt_tokio_1")]
         {
             use async_compression::tokio::write;
             use tokio::io::AsyncWriteExt;
             let mut encoder = write::BrotliEncoder::with_quality(
                 Vec::with_capacity(data.len()),
-                self.level.into(),
+                level.into_level(BALANCED_LEVEL),
             );
-            encoder.write_all(data.as_ref()).await?;
-            encoder.shutdown().await?;
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(compression_error(ALGORITHM))?;
+            encoder
+                .shutdown()
+                .await
+                .map_err(compression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(any(feature = "blocking", feature = "implicit-blocking"))]
@@ -46,10 +64,16 @@ impl CompressionStrategy for Brotli {
             use futures_util::AsyncWriteExt;
             let mut encoder = write::BrotliEncoder::with_quality(
                 Vec::with_capacity(data.len()),
-                self.level.into(),
+                level.into_level(BALANCED_LEVEL),
             );
-            encoder.write_all(data.as_ref()).await?;
-            encoder.close().await?;
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(compression_error(ALGORITHM))?;
+            encoder
+                .close()
+                .await
+                .map_err(compression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(feature = "rt_async-std_1")]
@@ -58,10 +82,16 @@ impl CompressionStrategy for Brotli {
             use async_std::io::WriteExt;
             let mut encoder = write::BrotliEncoder::with_quality(
                 Vec::with_capacity(data.len()),
-                self.level.into(),
+                level.into_level(BALANCED_LEVEL),
             );
-            encoder.write_all(data.as_ref()).await?;
-            encoder.flush().await?;
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(compression_error(ALGORITHM))?;
+            encoder
+                .flush()
+                .await
+                .map_err(compression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
     }
@@ -72,8 +102,14 @@ impl CompressionStrategy for Brotli {
             use async_compression::tokio::write;
             use tokio::io::AsyncWriteExt;
             let mut encoder = write::BrotliDecoder::new(Vec::with_capacity(data.len()));
-            encoder.write_all(data.as_ref()).await?;
-            encoder.shutdown().await?;
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
+            encoder
+                .shutdown()
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(any(feature = "blocking", feature = "implicit-blocking"))]
@@ -81,8 +117,14 @@ impl CompressionStrategy for Brotli {
             use async_compression::futures::write;
             use futures_util::AsyncWriteExt;
             let mut encoder = write::BrotliDecoder::new(Vec::with_capacity(data.len()));
-            encoder.write_all(data.as_ref()).await?;
-            encoder.close().await?;
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
+            encoder
+                .close()
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(feature = "rt_async-std_1")]
@@ -90,8 +132,14 @@ impl CompressionStrategy for Brotli {
             use async_compression::futures::write;
             use async_std::io::WriteExt;
             let mut encoder = write::BrotliDecoder::new(Vec::with_capacity(data.len()));
-            encoder.write_all(data.as_ref()).await?;
-            encoder.flush().await?;
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
+            encoder
+                .flush()
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
     }
@@ -100,7 +148,7 @@ impl CompressionStrategy for Brotli {
 #[cfg(test)]
 mod tests {
     use super::Brotli;
-    use crate::{async_test, traits::CompressionStrategy, utils::test::create_arb_data};
+    use crate::{async_test, traits::CompressionStrategy, utils::test::create_arb_data, Error};
 
     async_test! {
         async fn test_compression() {
@@ -110,5 +158,11 @@ mod tests {
             let decompressed = brotli.decompress(compressed).await.unwrap();
             assert_eq!(data.as_slice(), decompressed.as_ref());
         }
+
+        async fn test_decompression_error() {
+            let brotli = Brotli::default();
+            let result = brotli.decompress(b"not compressed".as_slice().into()).await;
+            assert!(matches!(result, Err(Error::DecompressionError { .. })));
+        }
     }
 }
@@ -7,6 +7,11 @@ pub enum CompressionLevel {
     Default,
     /// Fastest compression level for the given compression algorithm
     Fastest,
+    /// A sensible trade-off between speed and ratio for the given compression algorithm
+    ///
+    /// Unlike [Best](CompressionLevel::Best), this stays fast enough for general use,
+    /// e.g. level 9 for zstd, 6 for gzip and 6 for brotli.
+    Balanced,
     /// Specify a custom compression level, which will be clamped to the values
     /// accepted by the underlying compression library.
     Precise(i32),
@@ -19,7 +24,20 @@ impl From<CompressionLevel> for async_compression::Level {
             Best => async_compression::Level::Best,
             Default => async_compression::Level::Default,
             Fastest => async_compression::Level::Fastest,
+            Balanced => async_compression::Level::Default,
             Precise(level) => async_compression::Level::Precise(level),
         }
     }
 }
+
+impl CompressionLevel {
+    /// Convert into an [async_compression::Level], using `balanced` for
+    /// [Balanced](CompressionLevel::Balanced).
+    #[cfg(any(feature = "comp_zstd", feature = "comp_brotli", feature = "comp_gzip"))]
+    pub(crate) fn into_level(self, balanced: i32) -> async_compression::Level {
+        match self {
+            CompressionLevel::Balanced => async_compression::Level::Precise(balanced),
+            level => level.into(),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::compression_level::CompressionLevel;
+use super::{compression_error, compression_level::CompressionLevel, decompression_error};
 use crate::traits::CompressionStrategy;
 use crate::Result;
 use async_trait::async_trait;
@@ -25,37 +25,73 @@ impl Default for Gzip {
     }
 }
 
+const ALGORITHM: &str = "gzip";
+/// The level used for [CompressionLevel::Balanced].
+const BALANCED_LEVEL: i32 = 6;
+
 #[async_trait]
 impl CompressionStrategy for Gzip {
     async fn compress<'a>(&self, data: Cow<'a, [u8]>) -> Result<Cow<'a, [u8]>> {
+        self.compress_with_level(data, self.level).await
+    }
+
+    async fn compress_with_level<'a>(
+        &self,
+        data: Cow<'a, [u8]>,
+        level: CompressionLevel,
+    ) -> Result<Cow<'a, [u8]>> {
         #[cfg(feature = "rt_tokio_1")]
         {
             use async_compression::tokio::write;
             use tokio::io::AsyncWriteExt;
-            let mut encoder =
-                write::GzipEncoder::with_quality(Vec::with_capacity(data.len()), self.level.into());
-            encoder.write_all(data.as_ref()).await?;
-            encoder.shutdown().await?;
+            let mut encoder = write::GzipEncoder::with_quality(
+                Vec::with_capacity(data.len()),
+                level.into_level(BALANCED_LEVEL),
+            );
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(compression_error(ALGORITHM))?;
+            encoder
+                .shutdown()
+                .await
+                .map_err(compression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(any(feature = "blocking", feature = "implicit-blocking"))]
         {
             use async_compression::futures::write;
             use futures_util::AsyncWriteExt;
-            let mut encoder =
-                write::GzipEncoder::with_quality(Vec::with_capacity(data.len()), self.level.into());
-            encoder.write_all(data.as_ref()).await?;
-            encoder.close().await?;
+            let mut encoder = write::GzipEncoder::with_quality(
+                Vec::with_capacity(data.len()),
+                level.into_level(BALANCED_LEVEL),
+            );
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(compression_error(ALGORITHM))?;
+            encoder
+                .close()
+                .await
+                .map_err(compression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(feature = "rt_async-std_1")]
         {
             use async_compression::futures::write;
             use async_std::io::WriteExt;
-            let mut encoder =
-                write::GzipEncoder::with_quality(Vec::with_capacity(data.len()), self.level.into());
-            encoder.write_all(data.as_ref()).await?;
-            encoder.flush().await?;
+            let mut encoder = write::GzipEncoder::with_quality(
+                Vec::with_capacity(data.len()),
+                level.into_level(BALANCED_LEVEL),
+            );
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(compression_error(ALGORITHM))?;
+            encoder
+                .flush()
+                .await
+                .map_err(compression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
     }
@@ -66,8 +102,14 @@ impl CompressionStrategy for Gzip {
             use async_compression::tokio::write;
             use tokio::io::AsyncWriteExt;
             let mut encoder = write::GzipDecoder::new(Vec::with_capacity(data.len()));
-            encoder.write_all(data.as_ref()).await?;
-            encoder.shutdown().await?;
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
+            encoder
+                .shutdown()
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(any(feature = "blocking", feature = "implicit-blocking"))]
@@ -75,8 +117,14 @@ impl CompressionStrategy for Gzip {
             use async_compression::futures::write;
             use futures_util::AsyncWriteExt;
             let mut encoder = write::GzipDecoder::new(Vec::with_capacity(data.len()));
-            encoder.write_all(data.as_ref()).await?;
-            encoder.close().await?;
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
+            encoder
+                .close()
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(feature = "rt_async-std_1")]
@@ -84,8 +132,14 @@ impl CompressionStrategy for Gzip {
             use async_compression::futures::write;
             use async_std::io::WriteExt;
             let mut encoder = write::GzipDecoder::new(Vec::with_capacity(data.len()));
-            encoder.write_all(data.as_ref()).await?;
-            encoder.flush().await?;
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
+            encoder
+                .flush()
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
     }
@@ -94,7 +148,13 @@ impl CompressionStrategy for Gzip {
 #[cfg(test)]
 mod tests {
     use super::Gzip;
-    use crate::{async_test, traits::CompressionStrategy, utils::test::create_arb_data};
+    use crate::{
+        async_test,
+        strategies::Disk,
+        traits::CompressionStrategy,
+        utils::test::{create_arb_data, TempDir},
+        Cache, Error,
+    };
 
     async_test! {
         async fn test_compression() {
@@ -104,5 +164,21 @@ mod tests {
             let decompressed = gzip.decompress(compressed).await.unwrap();
             assert_eq!(data.as_slice(), decompressed.as_ref());
         }
+
+        async fn test_decompression_error() {
+            let gzip = Gzip::default();
+            let result = gzip.decompress(b"not compressed".as_slice().into()).await;
+            assert!(matches!(result, Err(Error::DecompressionError { .. })));
+        }
+
+        async fn test_recover_empty_file() {
+            let temp_dir = TempDir::new();
+            std::fs::write(temp_dir.as_ref().join("empty"), b"").unwrap();
+
+            // An empty file isn't a valid gzip stream, but is still a valid entry
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), Some(Gzip::default())).await.unwrap();
+            assert_eq!(cache.recover(|k| Some(k.to_string())).await.unwrap(), 1);
+            assert!(cache.get("empty".to_string()).await.unwrap().is_empty());
+        }
     }
 }
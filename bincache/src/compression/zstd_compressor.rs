@@ -1,19 +1,82 @@
-use super::compression_level::CompressionLevel;
+use super::{compression_error, compression_level::CompressionLevel, decompression_error};
 use crate::traits::CompressionStrategy;
 use crate::Result;
+use async_compression::zstd::CParameter;
 use async_trait::async_trait;
-use std::borrow::Cow;
+use std::{borrow::Cow, io, path::Path};
 
 /// A Compressor using Zstd
 #[derive(Debug)]
 pub struct Zstd {
     level: CompressionLevel,
+    dictionary: Option<Vec<u8>>,
+    long_distance_matching: bool,
+    window_log: Option<u32>,
 }
 
 impl Zstd {
     /// Creates a new Zstd Compressor with the given compression level
     pub fn new(level: CompressionLevel) -> Self {
-        Self { level }
+        Self {
+            level,
+            dictionary: None,
+            long_distance_matching: false,
+            window_log: None,
+        }
+    }
+
+    /// Use a pretrained dictionary loaded from a file, e.g. one produced by `zstd --train`.
+    ///
+    /// The same dictionary must be used to decompress entries compressed with it,
+    /// so keep it alongside any cache that is recovered from disk.
+    ///
+    /// Returns [Error::InvalidDictionary](crate::Error::InvalidDictionary) if the file
+    /// can't be read or isn't a zstd dictionary.
+    pub fn with_dictionary_file(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let invalid_dictionary = |source| crate::Error::InvalidDictionary {
+            path: path.to_path_buf(),
+            source,
+        };
+        let dictionary = std::fs::read(path).map_err(invalid_dictionary)?;
+        if !dictionary.starts_with(&DICTIONARY_MAGIC) {
+            return Err(invalid_dictionary(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a zstd dictionary",
+            )));
+        }
+        self.dictionary = Some(dictionary);
+        Ok(self)
+    }
+
+    /// Enable long distance matching, which finds redundancy further apart than
+    /// the regular match window. Improves the ratio of large, repetitive values.
+    ///
+    /// Ignored when a dictionary is used.
+    pub fn with_long_distance_matching(mut self, enabled: bool) -> Self {
+        self.long_distance_matching = enabled;
+        self
+    }
+
+    /// Set the size of the match window as a power of two.
+    ///
+    /// The window log is capped at 27, the largest window zstd decompresses by default.
+    /// Ignored when a dictionary is used.
+    pub fn with_window_log(mut self, window_log: u32) -> Self {
+        self.window_log = Some(window_log.min(MAX_WINDOW_LOG));
+        self
+    }
+
+    /// The compression parameters to use on top of the level.
+    fn params(&self) -> Vec<CParameter> {
+        let mut params = Vec::new();
+        if self.long_distance_matching {
+            params.push(CParameter::enable_long_distance_matching(true));
+        }
+        if let Some(window_log) = self.window_log {
+            params.push(CParameter::window_log(window_log));
+        }
+        params
     }
 }
 
@@ -22,41 +85,93 @@ impl Default for Zstd {
     fn default() -> Self {
         Self {
             level: CompressionLevel::Default,
+            dictionary: None,
+            long_distance_matching: false,
+            window_log: None,
         }
     }
 }
 
+const ALGORITHM: &str = "zstd";
+/// The level used for [CompressionLevel::Balanced].
+const BALANCED_LEVEL: i32 = 9;
+/// The largest window log zstd decompresses without raising its memory limit.
+const MAX_WINDOW_LOG: u32 = 27;
+/// The magic number zstd dictionaries start with.
+const DICTIONARY_MAGIC: [u8; 4] = 0xEC30A437_u32.to_le_bytes();
+
 #[async_trait]
 impl CompressionStrategy for Zstd {
     async fn compress<'a>(&self, data: Cow<'a, [u8]>) -> Result<Cow<'a, [u8]>> {
+        self.compress_with_level(data, self.level).await
+    }
+
+    async fn compress_with_level<'a>(
+        &self,
+        data: Cow<'a, [u8]>,
+        level: CompressionLevel,
+    ) -> Result<Cow<'a, [u8]>> {
         #[cfg(feature = "rt_tokio_1")]
         {
             use async_compression::tokio::write;
             use tokio::io::AsyncWriteExt;
-            let mut encoder =
-                write::ZstdEncoder::with_quality(Vec::with_capacity(data.len()), self.level.into());
-            encoder.write_all(data.as_ref()).await?;
-            encoder.shutdown().await?;
+            let buf = Vec::with_capacity(data.len());
+            let level = level.into_level(BALANCED_LEVEL);
+            let mut encoder = match &self.dictionary {
+                Some(dictionary) => write::ZstdEncoder::with_dict(buf, level, dictionary)
+                    .map_err(compression_error(ALGORITHM))?,
+                None => write::ZstdEncoder::with_quality_and_params(buf, level, &self.params()),
+            };
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(compression_error(ALGORITHM))?;
+            encoder
+                .shutdown()
+                .await
+                .map_err(compression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(any(feature = "blocking", feature = "implicit-blocking"))]
         {
             use async_compression::futures::write;
             use futures_util::AsyncWriteExt;
-            let mut encoder =
-                write::ZstdEncoder::with_quality(Vec::with_capacity(data.len()), self.level.into());
-            encoder.write_all(data.as_ref()).await?;
-            encoder.close().await?;
+            let buf = Vec::with_capacity(data.len());
+            let level = level.into_level(BALANCED_LEVEL);
+            let mut encoder = match &self.dictionary {
+                Some(dictionary) => write::ZstdEncoder::with_dict(buf, level, dictionary)
+                    .map_err(compression_error(ALGORITHM))?,
+                None => write::ZstdEncoder::with_quality_and_params(buf, level, &self.params()),
+            };
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(compression_error(ALGORITHM))?;
+            encoder
+                .close()
+                .await
+                .map_err(compression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(feature = "rt_async-std_1")]
         {
             use async_compression::futures::write;
             use async_std::io::WriteExt;
-            let mut encoder =
-                write::ZstdEncoder::with_quality(Vec::with_capacity(data.len()), self.level.into());
-            encoder.write_all(data.as_ref()).await?;
-            encoder.flush().await?;
+            let buf = Vec::with_capacity(data.len());
+            let level = level.into_level(BALANCED_LEVEL);
+            let mut encoder = match &self.dictionary {
+                Some(dictionary) => write::ZstdEncoder::with_dict(buf, level, dictionary)
+                    .map_err(compression_error(ALGORITHM))?,
+                None => write::ZstdEncoder::with_quality_and_params(buf, level, &self.params()),
+            };
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(compression_error(ALGORITHM))?;
+            encoder
+                .flush()
+                .await
+                .map_err(compression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
     }
@@ -66,27 +181,60 @@ impl CompressionStrategy for Zstd {
         {
             use async_compression::tokio::write;
             use tokio::io::AsyncWriteExt;
-            let mut encoder = write::ZstdDecoder::new(Vec::with_capacity(data.len()));
-            encoder.write_all(data.as_ref()).await?;
-            encoder.shutdown().await?;
+            let buf = Vec::with_capacity(data.len());
+            let mut encoder = match &self.dictionary {
+                Some(dictionary) => write::ZstdDecoder::with_dict(buf, dictionary)
+                    .map_err(decompression_error(ALGORITHM))?,
+                None => write::ZstdDecoder::new(buf),
+            };
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
+            encoder
+                .shutdown()
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(any(feature = "blocking", feature = "implicit-blocking"))]
         {
             use async_compression::futures::write;
             use futures_util::AsyncWriteExt;
-            let mut encoder = write::ZstdDecoder::new(Vec::with_capacity(data.len()));
-            encoder.write_all(data.as_ref()).await?;
-            encoder.close().await?;
+            let buf = Vec::with_capacity(data.len());
+            let mut encoder = match &self.dictionary {
+                Some(dictionary) => write::ZstdDecoder::with_dict(buf, dictionary)
+                    .map_err(decompression_error(ALGORITHM))?,
+                None => write::ZstdDecoder::new(buf),
+            };
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
+            encoder
+                .close()
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
         #[cfg(feature = "rt_async-std_1")]
         {
             use async_compression::futures::write;
             use async_std::io::WriteExt;
-            let mut encoder = write::ZstdDecoder::new(Vec::with_capacity(data.len()));
-            encoder.write_all(data.as_ref()).await?;
-            encoder.flush().await?;
+            let buf = Vec::with_capacity(data.len());
+            let mut encoder = match &self.dictionary {
+                Some(dictionary) => write::ZstdDecoder::with_dict(buf, dictionary)
+                    .map_err(decompression_error(ALGORITHM))?,
+                None => write::ZstdDecoder::new(buf),
+            };
+            encoder
+                .write_all(data.as_ref())
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
+            encoder
+                .flush()
+                .await
+                .map_err(decompression_error(ALGORITHM))?;
             return Ok(encoder.into_inner().into());
         }
     }
@@ -94,8 +242,14 @@ impl CompressionStrategy for Zstd {
 
 #[cfg(test)]
 mod tests {
-    use super::Zstd;
-    use crate::{async_test, traits::CompressionStrategy, utils::test::create_arb_data};
+    use super::{CompressionLevel, Zstd};
+    use crate::{
+        async_test,
+        strategies::{Disk, Memory},
+        traits::CompressionStrategy,
+        utils::test::{create_arb_data, TempDir},
+        Cache, CacheBuilder, EntryLocation, Error, NO_COMPRESSION,
+    };
 
     async_test! {
         async fn test_compression() {
@@ -105,5 +259,325 @@ mod tests {
             let decompressed = zstd.decompress(compressed).await.unwrap();
             assert_eq!(data.as_slice(), decompressed.as_ref());
         }
+
+        async fn test_estimated_stored_size() {
+            let data = create_arb_data(1024);
+            let mut cache = Cache::new(Memory::new(Some(2048), None), Some(Zstd::default())).await.unwrap();
+
+            let estimated_size = cache.estimated_stored_size(&data).await.unwrap();
+            assert!(estimated_size < data.len());
+
+            cache.put("foo", data).await.unwrap();
+            assert_eq!(cache.capacity().unwrap().used(), estimated_size);
+        }
+
+        async fn test_recompress() {
+            let data = create_arb_data(1024);
+            let mut cache = Cache::new(Memory::new(Some(2048), None), NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", data.clone()).await.unwrap();
+            assert_eq!(cache.capacity().unwrap().used(), 1024);
+
+            let cache = cache.recompress(Zstd::default()).await.unwrap();
+            assert!(cache.capacity().unwrap().used() < 1024);
+            assert_eq!(cache.get("foo").await.unwrap(), data.as_slice());
+        }
+
+        async fn test_decompression_error() {
+            let zstd = Zstd::default();
+            let result = zstd.decompress(b"not compressed".as_slice().into()).await;
+            assert!(matches!(result, Err(Error::DecompressionError { .. })));
+        }
+
+        async fn test_put_uncompressed() {
+            let data = create_arb_data(1024);
+            let mut cache = Cache::new(Memory::new(Some(4096), None), Some(Zstd::default())).await.unwrap();
+
+            cache.put_uncompressed("foo", data.clone()).await.unwrap();
+            assert_eq!(cache.capacity().unwrap().used(), 1024);
+            assert_eq!(cache.get("foo").await.unwrap(), data.as_slice());
+
+            cache.put("bar", data.clone()).await.unwrap();
+            assert_eq!(cache.get("bar").await.unwrap(), data.as_slice());
+
+            let cache = cache.recompress(Zstd::default()).await.unwrap();
+            assert_eq!(cache.get("foo").await.unwrap(), data.as_slice());
+            assert!(cache.capacity().unwrap().used() < 2048);
+        }
+
+        async fn test_uncompressed_byte_limit() {
+            let data = create_arb_data(1024);
+
+            let mut cache = CacheBuilder
+                .with_strategy(Memory::new(Some(2048), None))
+                .with_compression(Zstd::default())
+                .build()
+                .await
+                .unwrap();
+            for key in ["foo", "bar", "baz"] {
+                cache.put(key, data.clone()).await.unwrap();
+            }
+
+            let mut cache = CacheBuilder
+                .with_strategy(Memory::new(Some(2048), None))
+                .with_compression(Zstd::default())
+                .enforce_uncompressed_byte_limit(true)
+                .build()
+                .await
+                .unwrap();
+            cache.put("foo", data.clone()).await.unwrap();
+            cache.put("bar", data.clone()).await.unwrap();
+            assert!(matches!(
+                cache.put("baz", data.clone()).await,
+                Err(Error::LimitExceeded { .. })
+            ));
+
+            // Replacing an entry only counts the new value
+            cache.put("foo", data.clone()).await.unwrap();
+            cache.delete("bar").await.unwrap();
+            cache.put("baz", data.clone()).await.unwrap();
+        }
+
+        async fn test_balanced_level() {
+            let data = create_arb_data(1024);
+            let zstd = Zstd::new(CompressionLevel::Balanced);
+            let compressed = zstd.compress(data.clone().into()).await.unwrap();
+            assert!(compressed.len() < data.len());
+            let decompressed = zstd.decompress(compressed).await.unwrap();
+            assert_eq!(data.as_slice(), decompressed.as_ref());
+        }
+
+        async fn test_dictionary() {
+            let dictionary = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/zstd.dict");
+            let data = br#"{"id":1,"name":"user-1","email":"user1@example.com","active":true}"#.to_vec();
+
+            let mut cache = CacheBuilder
+                .with_strategy(Memory::default())
+                .with_compression(Zstd::default())
+                .with_compression_dictionary(dictionary)
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            cache.put("foo", data.clone()).await.unwrap();
+            assert_eq!(cache.get("foo").await.unwrap(), data.as_slice());
+
+            let zstd = Zstd::default().with_dictionary_file(dictionary).unwrap();
+            let compressed = zstd.compress(data.as_slice().into()).await.unwrap();
+            let result = Zstd::default().decompress(compressed).await;
+            assert!(matches!(result, Err(Error::DecompressionError { .. })));
+        }
+
+        async fn test_invalid_dictionary() {
+            let not_a_dictionary = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+            let result = Zstd::default().with_dictionary_file(not_a_dictionary);
+            assert!(matches!(result, Err(Error::InvalidDictionary { .. })));
+
+            let missing = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/missing.dict");
+            let result = Zstd::default().with_dictionary_file(missing);
+            assert!(matches!(result, Err(Error::InvalidDictionary { .. })));
+        }
+
+        async fn test_prefer_smaller() {
+            // Random bytes don't compress, so zstd only adds its framing
+            let data: Vec<u8> = (0..64).flat_map(|_| *uuid::Uuid::new_v4().as_bytes()).collect();
+
+            let mut cache = CacheBuilder
+                .with_strategy(Memory::new(Some(4096), None))
+                .with_compression(Zstd::default())
+                .build()
+                .await
+                .unwrap();
+            cache.put("foo", data.clone()).await.unwrap();
+            assert!(cache.capacity().unwrap().used() > data.len());
+
+            let mut cache = CacheBuilder
+                .with_strategy(Memory::new(Some(4096), None))
+                .with_compression(Zstd::default())
+                .prefer_smaller(true)
+                .build()
+                .await
+                .unwrap();
+            cache.put("foo", data.clone()).await.unwrap();
+            assert_eq!(cache.capacity().unwrap().used(), data.len());
+            assert_eq!(cache.get("foo").await.unwrap(), data.as_slice());
+
+            let compressible = create_arb_data(1024);
+            cache.put("bar", compressible.clone()).await.unwrap();
+            assert!(cache.capacity().unwrap().used() < data.len() + compressible.len());
+            assert_eq!(cache.get("bar").await.unwrap(), compressible.as_slice());
+        }
+
+        async fn test_recompress_prefer_smaller() {
+            let temp_dir = TempDir::new();
+            let dense: Vec<u8> = (0..64).flat_map(|_| *uuid::Uuid::new_v4().as_bytes()).collect();
+            let compressible = create_arb_data(1024);
+            {
+                let mut cache = CacheBuilder
+                    .with_strategy(Disk::new(temp_dir.as_ref(), Some(1 << 20), None))
+                    .with_compression(Zstd::default())
+                    .prefer_smaller(true)
+                    .build()
+                    .await
+                    .unwrap();
+                cache.put("dense".to_string(), dense.clone()).await.unwrap();
+                cache.put("compressible".to_string(), compressible.clone()).await.unwrap();
+
+                let cache = cache.recompress(Zstd::new(CompressionLevel::Best)).await.unwrap();
+                assert_eq!(cache.get("dense".to_string()).await.unwrap(), dense.as_slice());
+                assert_eq!(cache.get("compressible".to_string()).await.unwrap(), compressible.as_slice());
+                assert!(cache.capacity().unwrap().used() < dense.len() + compressible.len());
+            }
+
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), Some(Zstd::default())).await.unwrap();
+            assert_eq!(cache.recover(|key| Some(key.to_string())).await.unwrap(), 2);
+            assert_eq!(cache.get("dense".to_string()).await.unwrap(), dense.as_slice());
+            assert_eq!(cache.get("compressible".to_string()).await.unwrap(), compressible.as_slice());
+            let (_, info) = cache.get_detailed("dense".to_string()).await.unwrap();
+            assert!(!info.decompressed());
+        }
+
+        async fn test_recover_prefer_smaller() {
+            let temp_dir = TempDir::new();
+            let data: Vec<u8> = (0..64).flat_map(|_| *uuid::Uuid::new_v4().as_bytes()).collect();
+            {
+                let mut cache = CacheBuilder
+                    .with_strategy(Disk::new(temp_dir.as_ref(), None, None))
+                    .with_compression(Zstd::default())
+                    .prefer_smaller(true)
+                    .build()
+                    .await
+                    .unwrap();
+                cache.put("foo".to_string(), data.clone()).await.unwrap();
+            }
+
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), Some(Zstd::default())).await.unwrap();
+            assert_eq!(cache.recover(|key| Some(key.to_string())).await.unwrap(), 1);
+            assert_eq!(cache.get("foo".to_string()).await.unwrap(), data.as_slice());
+        }
+
+        async fn test_get_raw() {
+            let data = create_arb_data(1024);
+            let mut cache = Cache::new(Memory::default(), Some(Zstd::default())).await.unwrap();
+            cache.put("foo", data.clone()).await.unwrap();
+
+            let raw = cache.get_raw("foo").await.unwrap().into_owned();
+            assert!(raw.len() < data.len());
+            assert_eq!(Zstd::default().decompress(raw.into()).await.unwrap(), data.as_slice());
+        }
+
+        async fn test_memory_compressed_accounting() {
+            let data = create_arb_data(1024);
+            let mut cache = Cache::new(Memory::new(Some(4096), None), Some(Zstd::default())).await.unwrap();
+
+            cache.put("foo", data.clone()).await.unwrap();
+            let stored = cache.capacity().unwrap().used();
+            assert!(stored < data.len());
+            assert_eq!(cache.get("foo").await.unwrap(), data.as_slice());
+
+            // Overwriting an entry replaces its footprint instead of adding to it
+            cache.put("foo", data.clone()).await.unwrap();
+            assert_eq!(cache.capacity().unwrap().used(), stored);
+
+            cache.put("bar", data.clone()).await.unwrap();
+            assert_eq!(cache.capacity().unwrap().used(), stored * 2);
+
+            cache.delete("foo").await.unwrap();
+            assert_eq!(cache.take("bar").await.unwrap(), data);
+            assert_eq!(cache.capacity().unwrap().used(), 0);
+        }
+
+        async fn test_get_detailed() {
+            let data = create_arb_data(1024);
+            let mut cache = Cache::new(Memory::default(), Some(Zstd::default())).await.unwrap();
+            cache.put("foo", data.clone()).await.unwrap();
+            cache.put_uncompressed("bar", data.clone()).await.unwrap();
+
+            let (value, info) = cache.get_detailed("foo").await.unwrap();
+            assert_eq!(value, data.as_slice());
+            assert_eq!(info.location(), EntryLocation::Memory);
+            assert!(info.stored_len() < data.len());
+            assert_eq!(info.byte_len(), data.len());
+            assert!(info.decompressed());
+
+            let (_, info) = cache.get_detailed("bar").await.unwrap();
+            assert_eq!(info.stored_len(), data.len());
+            assert!(!info.decompressed());
+        }
+
+        async fn test_long_distance_matching() {
+            // A block repeated beyond the reach of a narrow match window
+            let block = create_arb_data(64 * 1024);
+            let data = [block.as_slice(), &vec![0; 256 * 1024], block.as_slice()].concat();
+
+            let narrow = Zstd::default().with_window_log(17);
+            let ldm = Zstd::default().with_window_log(20).with_long_distance_matching(true);
+
+            let narrow_len = narrow.compress(data.as_slice().into()).await.unwrap().len();
+            let compressed = ldm.compress(data.as_slice().into()).await.unwrap();
+            assert!(compressed.len() < narrow_len);
+            assert_eq!(ldm.decompress(compressed).await.unwrap(), data.as_slice());
+        }
+
+        async fn test_append_compressed() {
+            let mut cache = Cache::new(Memory::default(), Some(Zstd::default())).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            let result = cache.append("foo", b"bar").await;
+            assert!(matches!(result, Err(Error::Unsupported { .. })));
+
+            // Uncompressed entries can be appended to
+            cache.put_uncompressed("bar", b"bar".to_vec()).await.unwrap();
+            cache.append("bar", b"baz").await.unwrap();
+            cache.append_or_create("baz", b"baz").await.unwrap();
+            cache.append("baz", b"qux").await.unwrap();
+            assert_eq!(cache.get("bar").await.unwrap(), b"barbaz".as_slice());
+            assert_eq!(cache.get("baz").await.unwrap(), b"bazqux".as_slice());
+        }
+
+        async fn test_put_with_level() {
+            let data = [create_arb_data(4096), b"some text ".repeat(512)].concat();
+            let mut cache = Cache::new(Memory::default(), Some(Zstd::new(CompressionLevel::Fastest))).await.unwrap();
+
+            cache.put("fast", data.clone()).await.unwrap();
+            cache.put_with_level("best", data.clone(), CompressionLevel::Precise(19)).await.unwrap();
+
+            let fast = cache.get_raw("fast").await.unwrap().len();
+            let best = cache.get_raw("best").await.unwrap().len();
+            assert!(best < fast);
+            assert_eq!(cache.get("best").await.unwrap(), data.as_slice());
+        }
+
+        async fn test_recover_uncompressed() {
+            let temp_dir = TempDir::new();
+            let data = create_arb_data(1024);
+            {
+                let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), Some(Zstd::default())).await.unwrap();
+                cache.put_uncompressed("foo".to_string(), data.clone()).await.unwrap();
+                cache.put("bar".to_string(), data.clone()).await.unwrap();
+            }
+
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), Some(Zstd::default())).await.unwrap();
+            assert_eq!(cache.recover(|key| Some(key.to_string())).await.unwrap(), 2);
+            assert_eq!(cache.get("foo".to_string()).await.unwrap(), data.as_slice());
+            assert_eq!(cache.get("bar".to_string()).await.unwrap(), data.as_slice());
+
+            let (_, info) = cache.get_detailed("foo".to_string()).await.unwrap();
+            assert!(!info.decompressed());
+            let (_, info) = cache.get_detailed("bar".to_string()).await.unwrap();
+            assert!(info.decompressed());
+        }
+
+        async fn test_recompress_keeps_uncompressed() {
+            let data = create_arb_data(1024);
+            let mut cache = Cache::new(Memory::new(Some(2048), None), NO_COMPRESSION).await.unwrap();
+
+            cache.put_uncompressed("foo", data.clone()).await.unwrap();
+
+            let cache = cache.recompress(Zstd::default()).await.unwrap();
+            assert_eq!(cache.capacity().unwrap().used(), 1024);
+            let (value, info) = cache.get_detailed("foo").await.unwrap();
+            assert_eq!(value, data.as_slice());
+            assert!(!info.decompressed());
+        }
     }
 }
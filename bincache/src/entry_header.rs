@@ -0,0 +1,217 @@
+//! A versioned binary header for stored entries.
+//!
+//! Features that need per-entry metadata (compression markers, expiry, checksums)
+//! share this format instead of each inventing its own sidecar file.
+//!
+//! [Disk](crate::strategies::Disk) and [Hybrid](crate::strategies::Hybrid) write
+//! a header in front of every entry file. Files that don't start with a valid
+//! header, like the ones written before headers existed, are read as headerless.
+//!
+//! ## Layout
+//! All integers are little-endian.
+//!
+//! | Field          | Size | Present                      |
+//! |----------------|------|------------------------------|
+//! | Magic `BINC`   | 4    | Always                       |
+//! | Version        | 1    | Always                       |
+//! | Flags          | 1    | Always                       |
+//! | Original len   | 8    | Always                       |
+//! | Expires at     | 8    | If [FLAG_EXPIRES_AT] is set  |
+//! | Checksum       | 4    | If [FLAG_CHECKSUM] is set    |
+
+use crate::{Error, Result};
+
+/// The magic bytes every header starts with.
+pub const MAGIC: [u8; 4] = *b"BINC";
+/// The current header version.
+pub const VERSION: u8 = 1;
+
+/// The entry's value is compressed.
+pub const FLAG_COMPRESSED: u8 = 1 << 0;
+/// The entry's value is encrypted.
+pub const FLAG_ENCRYPTED: u8 = 1 << 1;
+/// The header contains an expiry time.
+pub const FLAG_EXPIRES_AT: u8 = 1 << 2;
+/// The header contains a checksum.
+pub const FLAG_CHECKSUM: u8 = 1 << 3;
+/// The strategy compressed the value when writing it to disk.
+pub const FLAG_DISK_COMPRESSED: u8 = 1 << 4;
+
+const KNOWN_FLAGS: u8 =
+    FLAG_COMPRESSED | FLAG_ENCRYPTED | FLAG_EXPIRES_AT | FLAG_CHECKSUM | FLAG_DISK_COMPRESSED;
+const FIXED_LEN: usize = MAGIC.len() + 1 + 1 + 8;
+/// The maximum number of bytes a header occupies.
+pub(crate) const MAX_LEN: usize = FIXED_LEN + 8 + 4;
+
+/// Metadata stored in front of an entry's value.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EntryHeader {
+    /// Whether the value is compressed.
+    pub compressed: bool,
+    /// Whether the value is encrypted.
+    pub encrypted: bool,
+    /// The length of the value before compression or encryption.
+    pub original_len: u64,
+    /// When the entry expires, in seconds since the Unix epoch.
+    pub expires_at: Option<u64>,
+    /// A checksum of the stored value.
+    pub checksum: Option<u32>,
+    /// Whether the strategy compressed the value when writing it to disk, e.g. with
+    /// [Hybrid::with_disk_compression](crate::strategies::Hybrid::with_disk_compression).
+    ///
+    /// The strategy has to decompress such values before [compressed](Self::compressed) applies.
+    pub disk_compressed: bool,
+}
+
+impl EntryHeader {
+    /// The number of bytes [encode](Self::encode) produces.
+    pub fn encoded_len(&self) -> usize {
+        FIXED_LEN + self.expires_at.map_or(0, |_| 8) + self.checksum.map_or(0, |_| 4)
+    }
+
+    /// Encode the header.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut flags = 0;
+        if self.compressed {
+            flags |= FLAG_COMPRESSED;
+        }
+        if self.encrypted {
+            flags |= FLAG_ENCRYPTED;
+        }
+        if self.expires_at.is_some() {
+            flags |= FLAG_EXPIRES_AT;
+        }
+        if self.checksum.is_some() {
+            flags |= FLAG_CHECKSUM;
+        }
+        if self.disk_compressed {
+            flags |= FLAG_DISK_COMPRESSED;
+        }
+
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.push(flags);
+        bytes.extend_from_slice(&self.original_len.to_le_bytes());
+        if let Some(expires_at) = self.expires_at {
+            bytes.extend_from_slice(&expires_at.to_le_bytes());
+        }
+        if let Some(checksum) = self.checksum {
+            bytes.extend_from_slice(&checksum.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decode a header from the start of `bytes`.
+    /// Returns the header along with the number of bytes it occupied,
+    /// so the value starts at that offset.
+    ///
+    /// Returns [Error::InvalidEntryHeader] if the bytes don't start with a
+    /// valid header of a supported version.
+    pub fn decode(bytes: &[u8]) -> Result<(Self, usize)> {
+        let mut reader = Reader(bytes);
+        if reader.take::<4>()? != MAGIC {
+            return Err(invalid("missing magic bytes"));
+        }
+        if reader.take::<1>()?[0] != VERSION {
+            return Err(invalid("unsupported version"));
+        }
+        let flags = reader.take::<1>()?[0];
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(invalid("unknown flags"));
+        }
+
+        let original_len = u64::from_le_bytes(reader.take()?);
+        let expires_at = match flags & FLAG_EXPIRES_AT {
+            0 => None,
+            _ => Some(u64::from_le_bytes(reader.take()?)),
+        };
+        let checksum = match flags & FLAG_CHECKSUM {
+            0 => None,
+            _ => Some(u32::from_le_bytes(reader.take()?)),
+        };
+
+        let header = EntryHeader {
+            compressed: flags & FLAG_COMPRESSED != 0,
+            encrypted: flags & FLAG_ENCRYPTED != 0,
+            original_len,
+            expires_at,
+            checksum,
+            disk_compressed: flags & FLAG_DISK_COMPRESSED != 0,
+        };
+        Ok((header, bytes.len() - reader.0.len()))
+    }
+}
+
+/// Reads fixed-size fields from the front of a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.0.len() < N {
+            return Err(invalid("truncated header"));
+        }
+        let (field, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(field.try_into().expect("field has N bytes"))
+    }
+}
+
+fn invalid(reason: &'static str) -> Error {
+    Error::InvalidEntryHeader { reason }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EntryHeader, FIXED_LEN};
+    use crate::Error;
+
+    #[test]
+    fn test_roundtrip() {
+        let headers = [
+            EntryHeader::default(),
+            EntryHeader {
+                compressed: true,
+                encrypted: true,
+                original_len: 1024,
+                expires_at: Some(1_700_000_000),
+                checksum: Some(0xdead_beef),
+                disk_compressed: true,
+            },
+            EntryHeader {
+                original_len: 3,
+                checksum: Some(42),
+                ..Default::default()
+            },
+        ];
+        for header in headers {
+            let mut bytes = header.encode();
+            assert_eq!(bytes.len(), header.encoded_len());
+            bytes.extend_from_slice(b"value");
+
+            let (decoded, offset) = EntryHeader::decode(&bytes).unwrap();
+            assert_eq!(decoded, header);
+            assert_eq!(&bytes[offset..], b"value");
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let bytes = EntryHeader {
+            expires_at: Some(1),
+            ..Default::default()
+        }
+        .encode();
+
+        let invalid = [
+            &b"not a header"[..],
+            &bytes[..FIXED_LEN],
+            &[b'B', b'I', b'N', b'C', 2, 0],
+            &[b'B', b'I', b'N', b'C', 1, 0xf0],
+        ];
+        for bytes in invalid {
+            let result = EntryHeader::decode(bytes);
+            assert!(matches!(result, Err(Error::InvalidEntryHeader { .. })));
+        }
+    }
+}
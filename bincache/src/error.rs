@@ -8,12 +8,98 @@ pub enum Error {
     #[error("Key not found in cache.")]
     KeyNotFound,
 
+    /// An entry already exists for the key, see [OnConflict::Reject](crate::OnConflict::Reject).
+    #[error("Key already exists in cache.")]
+    KeyExists,
+
     #[error("Cache limit exceeded: {limit_kind}")]
     LimitExceeded { limit_kind: Cow<'static, str> },
 
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
 
+    /// A key is too long to be used as a file name.
+    ///
+    /// Most file systems limit file names to 255 bytes.
+    #[error("Key too long: {len} bytes exceeds the file name limit of {limit} bytes")]
+    KeyTooLong {
+        /// The length of the encoded key.
+        len: usize,
+        /// The maximum file name length.
+        limit: usize,
+    },
+
+    /// Setting up a strategy failed, e.g. because its directory couldn't be created.
+    #[error("Setup failed for {}: {source}", path.display())]
+    SetupFailed {
+        /// The path that couldn't be set up.
+        path: std::path::PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// Compressing data failed.
+    #[error("{algorithm} compression failed: {source}")]
+    CompressionError {
+        /// The compression algorithm that failed.
+        algorithm: &'static str,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// Decompressing data failed, most likely because it is corrupt.
+    #[error("{algorithm} decompression failed: {source}")]
+    DecompressionError {
+        /// The compression algorithm that failed.
+        algorithm: &'static str,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// A compression dictionary couldn't be loaded.
+    #[error("Invalid compression dictionary {}: {source}", path.display())]
+    InvalidDictionary {
+        /// The path the dictionary was loaded from.
+        path: std::path::PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// An entry's data doesn't have the size it was stored with, e.g. because its
+    /// file was truncated.
+    #[error("Integrity check failed for {}: expected {expected} bytes, found {actual}", path.display())]
+    IntegrityError {
+        /// The path of the entry's file.
+        path: std::path::PathBuf,
+        /// The size the entry was stored with.
+        expected: usize,
+        /// The size that was read.
+        actual: usize,
+    },
+
+    /// An operation isn't supported by the strategy or for the entry.
+    #[error("Unsupported operation: {operation}")]
+    Unsupported {
+        /// The operation that isn't supported.
+        operation: &'static str,
+    },
+
+    /// A cache option was set to an invalid value.
+    #[error("Invalid value for {option}: {reason}")]
+    InvalidOption {
+        /// The name of the option.
+        option: &'static str,
+        /// Why the value is invalid.
+        reason: &'static str,
+    },
+
+    /// An [EntryHeader](crate::entry_header::EntryHeader) couldn't be decoded.
+    #[error("Invalid entry header: {reason}")]
+    InvalidEntryHeader {
+        /// Why the header is invalid.
+        reason: &'static str,
+    },
+
     /// An error variant for custom implementations.
     ///
     /// Use this to wrap any error type that implements `std::error::Error`.
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use crate::EntryLocation;
+
+/// Details about a single read from a [Cache](crate::Cache).
+///
+/// See [Cache::get_detailed](crate::Cache::get_detailed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetInfo {
+    pub(crate) location: EntryLocation,
+    pub(crate) stored_len: usize,
+    pub(crate) byte_len: usize,
+    pub(crate) decompressed: bool,
+    pub(crate) decompress_time: Duration,
+}
+
+impl GetInfo {
+    /// Get where the entry was read from.
+    pub fn location(&self) -> EntryLocation {
+        self.location
+    }
+
+    /// Get the number of bytes read from the strategy, i.e. the compressed size.
+    pub fn stored_len(&self) -> usize {
+        self.stored_len
+    }
+
+    /// Get the number of bytes returned, i.e. the decompressed size.
+    pub fn byte_len(&self) -> usize {
+        self.byte_len
+    }
+
+    /// Check whether the entry was decompressed.
+    pub fn decompressed(&self) -> bool {
+        self.decompressed
+    }
+
+    /// Get the time spent decompressing the entry.
+    pub fn decompress_time(&self) -> Duration {
+        self.decompress_time
+    }
+}
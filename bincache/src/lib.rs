@@ -40,6 +40,23 @@
 //! # }
 //! ```
 //!
+//! Aliased builders of disk-backed strategies can be configured in place:
+//!
+//! ```no_run
+//! use bincache::DiskCacheBuilder;
+//! use std::path::Path;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut cache = DiskCacheBuilder::default()
+//!     .with_cache_dir(Path::new("/tmp/bincache"))
+//!     .with_byte_limit(64 * 1024 * 1024)
+//!     .build().await?;
+//! cache.put("key", b"value".to_vec()).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! More advanced usage, using the builder directly:
 //!
 //! ```
@@ -81,11 +98,20 @@ mod cache;
 mod macros;
 mod noop;
 
+#[cfg(feature = "bench")]
+pub mod bench;
 pub mod cache_builder;
 pub mod cache_capacity;
+pub mod compact_report;
 pub mod compression;
+pub mod entry_header;
 pub mod error;
+pub mod get_info;
+pub mod prelude;
+pub mod read_buffer_stats;
+pub mod recovery_report;
 pub mod strategies;
+pub mod tier_stats;
 pub mod traits;
 pub mod utils;
 
@@ -93,12 +119,17 @@ pub(crate) use error::Result;
 pub(crate) use utils::disk_util as DiskUtil;
 
 // Export basic types
-pub use cache::Cache;
+pub use cache::{Cache, FlushMode, FlushOrder, OnConflict};
 pub use cache_builder::CacheBuilder;
 pub use cache_capacity::CacheCapacity;
+pub use compact_report::CompactReport;
 pub use compression::NO_COMPRESSION;
 pub use error::Error;
+pub use get_info::GetInfo;
 pub use noop::Noop;
+pub use read_buffer_stats::ReadBufferStats;
+pub use recovery_report::RecoveryReport;
+pub use tier_stats::TierStats;
 pub use traits::*;
 
 // Export typed caches and builders
@@ -8,13 +8,11 @@ macro_rules! reexport_strategy {
             pub use $crate::strategies::$strategy as [<$strategy Strategy>];
 
             const _: () = {
-                fn assert_default<T: Default>() {}
-                fn assert_strategy<T: $crate::traits::CacheStrategy>() {}
+                const fn assert_default<T: Default>() {}
+                const fn assert_strategy<T: $crate::traits::CacheStrategy>() {}
 
-                fn assert_all() {
-                    assert_default::<$crate::strategies::$strategy>();
-                    assert_strategy::<$crate::strategies::$strategy>();
-                }
+                assert_default::<$crate::strategies::$strategy>();
+                assert_strategy::<$crate::strategies::$strategy>();
             };
         }
     };
@@ -0,0 +1,63 @@
+//! Commonly used types and traits.
+//!
+//! Besides using a [Cache], this covers everything needed to implement a custom strategy.
+//! Only [CacheStrategy] is required. [RecoverableStrategy] and [FlushableStrategy]
+//! provide defaults for all of their methods, so implementing them is a matter of
+//! opting in and overriding what your backend supports.
+//!
+//! # Examples
+//! ```
+//! use bincache::prelude::*;
+//! use std::borrow::Cow;
+//!
+//! #[derive(Default)]
+//! struct Remote;
+//!
+//! #[async_trait]
+//! impl CacheStrategy for Remote {
+//!     type CacheEntry = Vec<u8>;
+//!
+//!     async fn put<'a, K, V>(&mut self, _key: &K, value: V) -> Result<Self::CacheEntry>
+//!     where
+//!         K: CacheKey + Sync + Send,
+//!         V: Into<Cow<'a, [u8]>> + Send,
+//!     {
+//!         Ok(value.into().into_owned())
+//!     }
+//!
+//!     async fn get<'a>(&self, entry: &'a Self::CacheEntry) -> Result<Cow<'a, [u8]>> {
+//!         Ok(entry.as_slice().into())
+//!     }
+//!
+//!     async fn take(&mut self, entry: Self::CacheEntry) -> Result<Vec<u8>> {
+//!         Ok(entry)
+//!     }
+//!
+//!     async fn delete(&mut self, _entry: Self::CacheEntry) -> Result<()> {
+//!         Ok(())
+//!     }
+//!
+//!     fn get_cache_capacity(&self) -> Option<CacheCapacity> {
+//!         None
+//!     }
+//! }
+//!
+//! // Opt in to recovery and flushing, using the provided defaults for now
+//! impl RecoverableStrategy for Remote {}
+//! impl FlushableStrategy for Remote {}
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> Result<()> {
+//! let mut cache = CacheBuilder.with_strategy(Remote).build().await?;
+//! cache.put("key", b"value".to_vec()).await?;
+//! assert_eq!(cache.flush().await?, 0);
+//! # Ok(())
+//! # }
+//! ```
+
+pub use async_trait::async_trait;
+
+pub use crate::{
+    error::Result, Cache, CacheBuilder, CacheCapacity, CacheKey, CacheStrategy,
+    CompressionStrategy, EntryLocation, Error, FlushableStrategy, RecoverableStrategy, Tier,
+};
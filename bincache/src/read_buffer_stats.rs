@@ -0,0 +1,31 @@
+/// Hit and miss counts of a strategy's read buffer.
+///
+/// See [Cache::read_buffer_stats](crate::Cache::read_buffer_stats).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReadBufferStats {
+    pub(crate) hits: usize,
+    pub(crate) misses: usize,
+}
+
+impl ReadBufferStats {
+    /// Get the number of reads served from the buffer.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Get the number of reads that weren't buffered.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Get the share of reads served from the buffer, between 0.0 and 1.0.
+    ///
+    /// Returns 0.0 if nothing was read yet.
+    pub fn hit_rate(&self) -> f64 {
+        let reads = self.hits + self.misses;
+        if reads == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / reads as f64
+    }
+}
@@ -0,0 +1,26 @@
+/// Summary of a recovery of a [Cache](crate::Cache).
+///
+/// See [Cache::recover_with_report](crate::Cache::recover_with_report).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryReport {
+    pub(crate) recovered: usize,
+    pub(crate) relocated_to_lost_found: usize,
+    pub(crate) read_errors: usize,
+}
+
+impl RecoveryReport {
+    /// Get the number of recovered and indexed entries.
+    pub fn recovered(&self) -> usize {
+        self.recovered
+    }
+
+    /// Get the number of files moved to `lost+found`, because their key couldn't be recovered.
+    pub fn relocated_to_lost_found(&self) -> usize {
+        self.relocated_to_lost_found
+    }
+
+    /// Get the number of files that were skipped, because they couldn't be read.
+    pub fn read_errors(&self) -> usize {
+        self.read_errors
+    }
+}
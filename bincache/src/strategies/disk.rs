@@ -2,12 +2,15 @@ use async_trait::async_trait;
 
 use std::{
     borrow::Cow,
+    collections::VecDeque,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
 use crate::{
-    traits::{CacheKey, CacheStrategy, RecoverableStrategy},
-    CacheCapacity, DiskUtil, Result,
+    entry_header::EntryHeader,
+    traits::{CacheKey, CacheStrategy, EntryLocation, KeyEncoding, RecoverableStrategy, Tier},
+    CacheCapacity, DiskUtil, ReadBufferStats, RecoveryReport, Result,
 };
 
 const LIMIT_KIND_BYTE: &str = "Stored bytes";
@@ -17,12 +20,71 @@ const LIMIT_KIND_ENTRY: &str = "Stored entries";
 pub struct Entry {
     path: PathBuf,
     byte_len: usize,
+    /// The header in front of the value, unless the file was written without one.
+    header: Option<EntryHeader>,
+}
+
+impl Entry {
+    /// Get the number of bytes the header occupies in front of the value.
+    fn header_len(&self) -> usize {
+        self.header.map_or(0, |header| header.encoded_len())
+    }
+}
+
+/// A small buffer of recently read entries, evicting the least recently used one.
+#[derive(Debug)]
+struct ReadBuffer {
+    /// The maximum number of buffered entries.
+    capacity: usize,
+    /// The buffered entries' data by path, most recently used first.
+    entries: VecDeque<(PathBuf, Arc<[u8]>)>,
+    /// The hit and miss counts.
+    stats: ReadBufferStats,
+}
+
+impl ReadBuffer {
+    /// Create an empty read buffer holding up to `capacity` entries.
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+            stats: ReadBufferStats::default(),
+        }
+    }
+
+    /// Get the buffered data of the entry at `path`, marking it as recently used.
+    fn get(&mut self, path: &Path) -> Option<Arc<[u8]>> {
+        let index = self.entries.iter().position(|(p, _)| p == path)?;
+        let entry = self.entries.remove(index)?;
+        let data = Arc::clone(&entry.1);
+        self.entries.push_front(entry);
+        self.stats.hits += 1;
+        Some(data)
+    }
+
+    /// Buffer the data of an entry read from disk, evicting the least recently used entry if full.
+    fn insert(&mut self, path: &Path, data: &[u8]) {
+        self.stats.misses += 1;
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(path);
+        self.entries.truncate(self.capacity - 1);
+        self.entries.push_front((path.to_owned(), Arc::from(data)));
+    }
+
+    /// Drop the buffered data of the entry at `path`, e.g. because it changed.
+    fn remove(&mut self, path: &Path) {
+        self.entries.retain(|(p, _)| p != path);
+    }
 }
 
 /// Disk-based cache strategy.
 ///
 /// This strategy stores entries on disk. It can be configured to limit the
 /// number of bytes and/or entries that can be stored.
+///
+/// The byte limit applies to the stored, i.e. compressed, size of entries.
 #[derive(Debug)]
 pub struct Disk {
     /// The directory where entries are stored.
@@ -35,6 +97,22 @@ pub struct Disk {
     current_byte_count: usize,
     /// The current number of entries stored.
     current_entry_count: usize,
+    /// The permissions of the cache directory (Unix only).
+    dir_mode: Option<u32>,
+    /// The permissions of created entry files (Unix only).
+    file_mode: Option<u32>,
+    /// How keys are encoded as file names.
+    key_encoding: KeyEncoding,
+    /// The directory taken and deleted entry files are moved to, instead of being removed.
+    archive_dir: Option<PathBuf>,
+    /// The directory entry files are written to before being moved into place.
+    temp_dir: Option<PathBuf>,
+    /// Check the size of entries when reading them.
+    strict_reads: bool,
+    /// The number of bytes each entry occupies in addition to its data.
+    entry_overhead: usize,
+    /// The buffer of recently read entries.
+    read_buffer: Option<Mutex<ReadBuffer>>,
 }
 
 impl Disk {
@@ -51,6 +129,252 @@ impl Disk {
             ..Default::default()
         }
     }
+
+    /// Set the directory where entries are stored.
+    pub fn with_cache_dir<'a>(mut self, cache_dir: impl Into<Cow<'a, Path>>) -> Self {
+        self.cache_dir = cache_dir.into().into_owned();
+        self
+    }
+
+    /// Set the maximum number of bytes that can be stored.
+    pub fn with_byte_limit(mut self, byte_limit: usize) -> Self {
+        self.byte_limit = Some(byte_limit);
+        self
+    }
+
+    /// Set the maximum number of entries that can be stored.
+    pub fn with_entry_limit(mut self, entry_limit: usize) -> Self {
+        self.entry_limit = Some(entry_limit);
+        self
+    }
+
+    /// Set the permissions of the cache directory, e.g. `0o700`.
+    ///
+    /// This has no effect on non-Unix platforms.
+    pub fn with_dir_mode(mut self, mode: u32) -> Self {
+        self.dir_mode = Some(mode);
+        self
+    }
+
+    /// Set the permissions of created entry files, e.g. `0o600`.
+    ///
+    /// This has no effect on non-Unix platforms.
+    pub fn with_file_mode(mut self, mode: u32) -> Self {
+        self.file_mode = Some(mode);
+        self
+    }
+
+    /// Set how keys are encoded as file names.
+    ///
+    /// Recovered entries are identified by their file name, so the key closure
+    /// passed to recovery receives the encoded key.
+    pub fn with_key_encoding(mut self, key_encoding: KeyEncoding) -> Self {
+        self.key_encoding = key_encoding;
+        self
+    }
+
+    /// Move the files of taken and deleted entries into `archive_dir` instead of removing them.
+    ///
+    /// Files are moved by renaming them, so `archive_dir` should be on the same
+    /// file system as the cache directory. Archived files with the same name are replaced.
+    pub fn archive_taken_to<'a>(mut self, archive_dir: impl Into<Cow<'a, Path>>) -> Self {
+        self.archive_dir = Some(archive_dir.into().into_owned());
+        self
+    }
+
+    /// Write entry files to `temp_dir` first, then move them into the cache directory.
+    ///
+    /// Entry files are always written to a temporary file first, so readers never
+    /// see partially written entries. By default, that file is created in a `.tmp`
+    /// directory inside the cache directory. If `temp_dir` is on a different file
+    /// system, files are copied instead, which isn't atomic. `temp_dir` must not be
+    /// the cache directory, or setting up the cache fails.
+    ///
+    /// The copy is a blocking call, so with an async runtime it holds an executor
+    /// thread for as long as copying the entry takes. Keep `temp_dir` on the cache
+    /// directory's file system when storing large entries.
+    pub fn with_temp_dir<'a>(mut self, temp_dir: impl Into<Cow<'a, Path>>) -> Self {
+        self.temp_dir = Some(temp_dir.into().into_owned());
+        self
+    }
+
+    /// Check that entries read from disk have the size they were stored with.
+    ///
+    /// Reading a truncated or otherwise resized file fails with
+    /// [Error::IntegrityError](crate::Error::IntegrityError) instead of returning
+    /// the wrong data. Taking such an entry removes it, and
+    /// [Cache::remove_corrupted](crate::Cache::remove_corrupted) removes all of them.
+    pub fn with_strict_reads(mut self, strict_reads: bool) -> Self {
+        self.strict_reads = strict_reads;
+        self
+    }
+
+    /// Set the number of bytes each entry occupies on disk in addition to its data.
+    ///
+    /// Small files take up at least a file system block, so a cache of many small
+    /// entries uses more disk space than its byte count suggests. Setting this to
+    /// the file system's block size, e.g. `4096`, makes the byte limit account for it.
+    pub fn with_entry_overhead(mut self, entry_overhead: usize) -> Self {
+        self.entry_overhead = entry_overhead;
+        self
+    }
+
+    /// Keep the data of the `entry_count` most recently read entries in memory.
+    ///
+    /// Reads of buffered entries are served without touching the disk, and
+    /// [Cache::get_shared](crate::Cache::get_shared) hands out uncompressed ones
+    /// without copying them. Unlike [Hybrid](crate::strategies::Hybrid), the
+    /// buffer only speeds up reads: every entry is still stored on disk, and the
+    /// least recently read entry is evicted once the buffer is full.
+    ///
+    /// Buffered data doesn't count towards the byte limit. Use
+    /// [Cache::read_buffer_stats](crate::Cache::read_buffer_stats) to check the hit rate.
+    pub fn with_read_buffer(mut self, entry_count: usize) -> Self {
+        self.read_buffer = Some(Mutex::new(ReadBuffer::new(entry_count)));
+        self
+    }
+
+    /// Run `f` on the read buffer, if there is one.
+    fn with_buffer<T>(&self, f: impl FnOnce(&mut ReadBuffer) -> T) -> Option<T> {
+        let read_buffer = self.read_buffer.as_ref()?;
+        let mut read_buffer = read_buffer.lock().unwrap_or_else(|err| err.into_inner());
+        Some(f(&mut read_buffer))
+    }
+
+    /// Write an entry's file, releasing the `replaced` entry once that succeeded.
+    async fn store<K>(
+        &mut self,
+        key: &K,
+        value: &[u8],
+        header: &EntryHeader,
+        replaced: Option<&Entry>,
+    ) -> Result<Entry>
+    where
+        K: CacheKey + Sync + Send,
+    {
+        let byte_len = value.len();
+
+        // The replaced entry's space is free once the new value is written
+        let (freed_byte_count, freed_entry_count) = match replaced {
+            Some(entry) => (entry.byte_len.saturating_add(self.entry_overhead), 1),
+            None => (0, 0),
+        };
+
+        // Check if the byte limit has been reached.
+        if let Some(byte_limit) = self.byte_limit {
+            let entry_byte_len = byte_len.saturating_add(self.entry_overhead);
+            let used_byte_count = self.used_byte_count().saturating_sub(freed_byte_count);
+            if used_byte_count.saturating_add(entry_byte_len) > byte_limit {
+                return Err(crate::Error::LimitExceeded {
+                    limit_kind: LIMIT_KIND_BYTE.into(),
+                });
+            }
+        }
+
+        // Check if entry limit has been reached.
+        if let Some(entry_limit) = self.entry_limit {
+            let entry_count = self.current_entry_count.saturating_sub(freed_entry_count);
+            if entry_count.saturating_add(1) > entry_limit {
+                return Err(crate::Error::LimitExceeded {
+                    limit_kind: LIMIT_KIND_ENTRY.into(),
+                });
+            }
+        }
+
+        // Write to disk
+        let path = DiskUtil::entry_path(&self.cache_dir, self.key_encoding.encode(key))?;
+        DiskUtil::write_atomic(
+            &path,
+            Some(header),
+            value,
+            self.file_mode,
+            self.temp_dir.as_deref(),
+        )
+        .await?;
+        self.with_buffer(|read_buffer| read_buffer.remove(&path));
+
+        // Update limits
+        if let Some(replaced) = replaced {
+            self.decrement_limits(replaced.byte_len);
+        }
+        self.increment_limits(byte_len);
+
+        // The replaced file was overwritten, unless it has another name. Removing
+        // it is best effort, as the new entry is stored either way.
+        if let Some(replaced) = replaced.filter(|replaced| replaced.path != path) {
+            self.with_buffer(|read_buffer| read_buffer.remove(&replaced.path));
+            _ = DiskUtil::delete_or_archive(&replaced.path, self.archive_dir.as_deref()).await;
+        }
+
+        Ok(Entry {
+            path,
+            byte_len,
+            header: Some(*header),
+        })
+    }
+
+    /// Get the number of bytes used by all entries, including their overhead.
+    fn used_byte_count(&self) -> usize {
+        self.current_entry_count
+            .saturating_mul(self.entry_overhead)
+            .saturating_add(self.current_byte_count)
+    }
+
+    /// Check that data read for an entry has the expected size, if reads are strict.
+    fn check_len(&self, entry: &Entry, data: &[u8]) -> Result<()> {
+        if self.strict_reads && data.len() != entry.byte_len {
+            return Err(crate::Error::IntegrityError {
+                path: entry.path.clone(),
+                expected: entry.byte_len,
+                actual: data.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Increment the limit counters for a newly stored entry.
+    fn increment_limits(&mut self, byte_len: usize) {
+        self.current_byte_count = self.current_byte_count.saturating_add(byte_len);
+        self.current_entry_count = self.current_entry_count.saturating_add(1);
+    }
+
+    /// Decrement the limit counters for a removed entry.
+    fn decrement_limits(&mut self, byte_len: usize) {
+        debug_assert!(self.current_byte_count >= byte_len, "byte count underflow");
+        debug_assert!(self.current_entry_count >= 1, "entry count underflow");
+        self.current_byte_count = self.current_byte_count.saturating_sub(byte_len);
+        self.current_entry_count = self.current_entry_count.saturating_sub(1);
+    }
+
+    /// Index recovered files, counting them towards the limits.
+    ///
+    /// Files compressed with a disk compressor, e.g. by
+    /// [Hybrid](crate::strategies::Hybrid), are skipped, since their data can't be read.
+    fn index_files<K>(
+        &mut self,
+        files: Vec<(K, PathBuf, usize, Option<EntryHeader>)>,
+    ) -> Vec<(K, Entry)> {
+        let mut entries = Vec::with_capacity(files.len());
+        for (key, path, byte_len, header) in files {
+            if header.map_or(false, |header| header.disk_compressed) {
+                continue;
+            }
+
+            // Increment limits
+            self.increment_limits(byte_len);
+
+            // Push entry
+            entries.push((
+                key,
+                Entry {
+                    path,
+                    byte_len,
+                    header,
+                },
+            ));
+        }
+        entries
+    }
 }
 
 impl Default for Disk {
@@ -61,6 +385,14 @@ impl Default for Disk {
             entry_limit: None,
             current_byte_count: 0,
             current_entry_count: 0,
+            dir_mode: None,
+            file_mode: None,
+            key_encoding: KeyEncoding::default(),
+            archive_dir: None,
+            temp_dir: None,
+            strict_reads: false,
+            entry_overhead: 0,
+            read_buffer: None,
         }
     }
 }
@@ -70,7 +402,16 @@ impl CacheStrategy for Disk {
     type CacheEntry = Entry;
 
     async fn setup(&mut self) -> Result<()> {
-        DiskUtil::create_dir(&self.cache_dir).await
+        DiskUtil::setup_dir(&self.cache_dir, self.dir_mode).await?;
+        if let Some(archive_dir) = &self.archive_dir {
+            DiskUtil::setup_dir(archive_dir, self.dir_mode).await?;
+        }
+        let temp_dir = self
+            .temp_dir
+            .get_or_insert_with(|| self.cache_dir.join(DiskUtil::TEMP_DIR_NAME));
+        DiskUtil::setup_dir(&*temp_dir, self.dir_mode).await?;
+        DiskUtil::check_temp_dir(&self.cache_dir, temp_dir)?;
+        Ok(())
     }
 
     async fn put<'a, K, V>(&mut self, key: &K, value: V) -> Result<Self::CacheEntry>
@@ -79,134 +420,321 @@ impl CacheStrategy for Disk {
         V: Into<Cow<'a, [u8]>> + Send,
     {
         let value = value.into();
-        let byte_len = value.as_ref().len();
+        let header = EntryHeader {
+            original_len: value.len() as u64,
+            ..Default::default()
+        };
+        self.store(key, &value, &header, None).await
+    }
+
+    async fn put_with_header<'a, K, V>(
+        &mut self,
+        key: &K,
+        value: V,
+        header: &EntryHeader,
+    ) -> Result<Self::CacheEntry>
+    where
+        K: CacheKey + Sync + Send,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        self.store(key, value.into().as_ref(), header, None).await
+    }
+
+    async fn replace<'a, K, V>(
+        &mut self,
+        key: &K,
+        old: &Self::CacheEntry,
+        value: V,
+    ) -> Result<Self::CacheEntry>
+    where
+        K: CacheKey + Sync + Send,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        let value = value.into();
+        let header = EntryHeader {
+            original_len: value.len() as u64,
+            ..Default::default()
+        };
+        self.store(key, &value, &header, Some(old)).await
+    }
+
+    async fn replace_with_header<'a, K, V>(
+        &mut self,
+        key: &K,
+        old: &Self::CacheEntry,
+        value: V,
+        header: &EntryHeader,
+    ) -> Result<Self::CacheEntry>
+    where
+        K: CacheKey + Sync + Send,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        self.store(key, value.into().as_ref(), header, Some(old))
+            .await
+    }
+
+    fn entry_header(&self, entry: &Self::CacheEntry) -> Option<EntryHeader> {
+        entry.header
+    }
+
+    async fn get<'a>(&self, entry: &'a Self::CacheEntry) -> Result<Cow<'a, [u8]>> {
+        if let Some(data) = self.get_shared(entry) {
+            return Ok(Cow::Owned(data.to_vec()));
+        }
+
+        let (_, data) = DiskUtil::read(&entry.path, Some(entry.byte_len)).await?;
+        self.check_len(entry, &data)?;
+        self.with_buffer(|read_buffer| read_buffer.insert(&entry.path, &data));
+        Ok(Cow::Owned(data))
+    }
+
+    async fn take(&mut self, entry: Self::CacheEntry) -> Result<Vec<u8>> {
+        let (_, data) = DiskUtil::read(&entry.path, Some(entry.byte_len)).await?;
+        let checked = self.check_len(&entry, &data);
+        self.delete(entry).await?;
+        checked?;
+
+        Ok(data)
+    }
+
+    async fn verify(&self, entry: &Self::CacheEntry) -> Result<bool> {
+        let file_len = DiskUtil::file_len(&entry.path).await?;
+        Ok(file_len == Some((entry.header_len() + entry.byte_len) as u64))
+    }
+
+    async fn delete(&mut self, entry: Self::CacheEntry) -> Result<()> {
+        DiskUtil::delete_or_archive(&entry.path, self.archive_dir.as_deref()).await?;
+        self.with_buffer(|read_buffer| read_buffer.remove(&entry.path));
+
+        // Decrement limits
+        self.decrement_limits(entry.byte_len);
 
+        Ok(())
+    }
+
+    async fn append(&mut self, entry: &mut Self::CacheEntry, data: &[u8]) -> Result<()> {
         // Check if the byte limit has been reached.
         if let Some(byte_limit) = self.byte_limit {
-            if self.current_byte_count + byte_len > byte_limit {
+            if self.used_byte_count().saturating_add(data.len()) > byte_limit {
                 return Err(crate::Error::LimitExceeded {
                     limit_kind: LIMIT_KIND_BYTE.into(),
                 });
             }
         }
 
-        // Check if entry limit has been reached.
-        if let Some(entry_limit) = self.entry_limit {
-            if self.current_entry_count + 1 > entry_limit {
-                return Err(crate::Error::LimitExceeded {
-                    limit_kind: LIMIT_KIND_ENTRY.into(),
-                });
-            }
-        }
-
-        // Write to disk
-        let path = self.cache_dir.join(key.to_key());
-        DiskUtil::write(&path, value.as_ref()).await?;
+        // The header isn't rewritten, since the length of uncompressed
+        // values is taken from the file on recovery
+        DiskUtil::append(&entry.path, data).await?;
+        self.with_buffer(|read_buffer| read_buffer.remove(&entry.path));
+        entry.byte_len += data.len();
 
         // Increment limits
-        self.current_byte_count += byte_len;
-        self.current_entry_count += 1;
+        self.current_byte_count = self.current_byte_count.saturating_add(data.len());
 
-        Ok(Entry { path, byte_len })
+        Ok(())
     }
 
-    async fn get<'a>(&self, entry: &'a Self::CacheEntry) -> Result<Cow<'a, [u8]>> {
-        DiskUtil::read(&entry.path, Some(entry.byte_len))
-            .await
-            .map(Cow::Owned)
+    fn get_shared(&self, entry: &Self::CacheEntry) -> Option<Arc<[u8]>> {
+        self.with_buffer(|read_buffer| read_buffer.get(&entry.path))
+            .flatten()
     }
 
-    async fn take(&mut self, entry: Self::CacheEntry) -> Result<Vec<u8>> {
-        let data = DiskUtil::read(&entry.path, Some(entry.byte_len)).await?;
-        self.delete(entry).await?;
+    fn stored_byte_len(&self, entry: &Self::CacheEntry) -> Option<usize> {
+        Some(entry.byte_len)
+    }
 
-        Ok(data)
+    fn recount(&mut self, entries: Vec<&Self::CacheEntry>) {
+        self.current_byte_count = entries.iter().map(|entry| entry.byte_len).sum();
+        self.current_entry_count = entries.len();
     }
 
-    async fn delete(&mut self, entry: Self::CacheEntry) -> Result<()> {
-        DiskUtil::delete(&entry.path).await?;
+    fn entry_overhead(&self) -> usize {
+        self.entry_overhead
+    }
 
-        // Decrement limits
-        self.current_byte_count -= entry.byte_len;
-        self.current_entry_count -= 1;
+    fn entry_location(&self, entry: &Self::CacheEntry) -> EntryLocation {
+        _ = entry;
+        EntryLocation::Disk
+    }
 
-        Ok(())
+    fn entry_path<'a>(&self, entry: &'a Self::CacheEntry) -> Option<(&'a Path, u64)> {
+        Some((&entry.path, entry.header_len() as u64))
+    }
+
+    fn cache_dir(&self) -> Option<&Path> {
+        Some(&self.cache_dir)
+    }
+
+    fn is_persistent(&self) -> bool {
+        true
     }
 
     fn get_cache_capacity(&self) -> Option<CacheCapacity> {
         self.byte_limit
-            .map(|byte_limit| CacheCapacity::new(byte_limit, self.current_byte_count))
+            .map(|byte_limit| CacheCapacity::new(byte_limit, self.used_byte_count()))
+    }
+
+    fn read_buffer_stats(&self) -> Option<ReadBufferStats> {
+        self.with_buffer(|read_buffer| read_buffer.stats)
+    }
+
+    fn tier_capacity(&self, tier: Tier) -> Option<CacheCapacity> {
+        match tier {
+            Tier::Memory => None,
+            Tier::Disk => self.get_cache_capacity(),
+        }
     }
 }
 
 #[async_trait]
 impl RecoverableStrategy for Disk {
-    async fn recover<K, F>(&mut self, mut recover_key: F) -> Result<Vec<(K, Self::CacheEntry)>>
+    async fn recover<K, F>(&mut self, recover_key: F) -> Result<Vec<(K, Self::CacheEntry)>>
     where
         K: Send,
         F: Fn(&str) -> Option<K> + Send,
     {
-        // Create the `lost+found` directory
-        let lost_found_dir = self.cache_dir.join("lost+found");
-        std::fs::create_dir_all(&lost_found_dir)?;
-
-        // Closure to move files to the `lost+found` directory
-        let move_to_lost_found = |source: &Path| {
-            // We explcitly ignore any errors here, as we don't want to fail
-            // the entire recovery process because of a single file.
-            let Some(file_name) = source.file_name() else {
-                return;
-            };
-            let target_path = lost_found_dir.join(file_name);
-            _ = std::fs::rename(source, target_path);
-        };
+        self.recover_filtered(recover_key, |_| true).await
+    }
 
-        // Iterate over all files in the cache directory
-        let mut entries = Vec::new();
-        for entry in std::fs::read_dir(&self.cache_dir)?.filter_map(|e| e.ok()) {
-            let path = entry.path();
+    async fn recover_filtered<K, F, P>(
+        &mut self,
+        recover_key: F,
+        should_consider: P,
+    ) -> Result<Vec<(K, Self::CacheEntry)>>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+        P: Fn(&str) -> bool + Send,
+    {
+        let files = DiskUtil::recover_dir(&self.cache_dir, recover_key, should_consider).await?;
 
-            // Skip directories
-            if path.is_dir() {
-                continue;
-            }
+        Ok(self.index_files(files))
+    }
 
-            // If key recovery fails, we move the entry to the `lost+found` directory.
-            let Some(key) = path
-                .file_name()
-                .and_then(|p| p.to_str())
-                .and_then(&mut recover_key)
-            else {
-                move_to_lost_found(&path);
-                continue;
-            };
+    async fn recover_with_report<K, F>(
+        &mut self,
+        recover_key: F,
+    ) -> Result<(Vec<(K, Self::CacheEntry)>, RecoveryReport)>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        let (files, report) =
+            DiskUtil::recover_dir_with_report(&self.cache_dir, recover_key, |_| true).await?;
 
-            // Read file
-            let buf = DiskUtil::read(&path, None).await?;
+        Ok((self.index_files(files), report))
+    }
 
-            // Increment limits
-            self.current_byte_count += buf.len();
-            self.current_entry_count += 1;
+    async fn recover_fast<K, F>(&mut self, recover_key: F) -> Result<Vec<(K, Self::CacheEntry)>>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        let files = DiskUtil::recover_dir_fast(&self.cache_dir, recover_key, |_| true).await?;
 
-            // Push entry
-            entries.push((
-                key,
-                Entry {
-                    path,
-                    byte_len: buf.len(),
-                },
-            ));
-        }
+        Ok(self.index_files(files))
+    }
 
-        // Return recovered entries
-        Ok(entries)
+    async fn recover_lost_found<K, F>(
+        &mut self,
+        recover_key: F,
+    ) -> Result<Vec<(K, Self::CacheEntry)>>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        let files = DiskUtil::recover_lost_found(&self.cache_dir, recover_key).await?;
+
+        Ok(self.index_files(files))
+    }
+
+    fn forget(&mut self, entry: Self::CacheEntry) {
+        self.decrement_limits(entry.byte_len);
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Disk, LIMIT_KIND_BYTE, LIMIT_KIND_ENTRY};
-    use crate::{async_test, utils::test::TempDir, Cache, Error, NO_COMPRESSION};
+    use super::{Disk, Entry, LIMIT_KIND_BYTE, LIMIT_KIND_ENTRY};
+    use crate::{
+        async_test,
+        entry_header::EntryHeader,
+        strategies::Memory,
+        utils::test::{read_entry_file, TempDir},
+        Cache, CacheCapacity, CacheKey, CacheStrategy, Error, KeyEncoding, Result, NO_COMPRESSION,
+    };
+    use async_trait::async_trait;
+    use std::{borrow::Cow, time::Duration};
+
+    /// A [Disk] strategy whose replaces stay pending before writing while `stalled` is set,
+    /// so a put can be dropped at a known point.
+    struct StalledDisk {
+        disk: Disk,
+        stalled: bool,
+    }
+
+    #[async_trait]
+    impl CacheStrategy for StalledDisk {
+        type CacheEntry = Entry;
+
+        async fn setup(&mut self) -> Result<()> {
+            self.disk.setup().await
+        }
+
+        async fn put<'a, K, V>(&mut self, key: &K, value: V) -> Result<Entry>
+        where
+            K: CacheKey + Sync + Send,
+            V: Into<Cow<'a, [u8]>> + Send,
+        {
+            self.disk.put(key, value).await
+        }
+
+        async fn put_with_header<'a, K, V>(
+            &mut self,
+            key: &K,
+            value: V,
+            header: &EntryHeader,
+        ) -> Result<Entry>
+        where
+            K: CacheKey + Sync + Send,
+            V: Into<Cow<'a, [u8]>> + Send,
+        {
+            self.disk.put_with_header(key, value, header).await
+        }
+
+        async fn replace_with_header<'a, K, V>(
+            &mut self,
+            key: &K,
+            old: &Entry,
+            value: V,
+            header: &EntryHeader,
+        ) -> Result<Entry>
+        where
+            K: CacheKey + Sync + Send,
+            V: Into<Cow<'a, [u8]>> + Send,
+        {
+            if self.stalled {
+                std::future::pending::<()>().await;
+            }
+            self.disk.replace_with_header(key, old, value, header).await
+        }
+
+        async fn get<'a>(&self, entry: &'a Entry) -> Result<Cow<'a, [u8]>> {
+            self.disk.get(entry).await
+        }
+
+        async fn take(&mut self, entry: Entry) -> Result<Vec<u8>> {
+            self.disk.take(entry).await
+        }
+
+        async fn delete(&mut self, entry: Entry) -> Result<()> {
+            self.disk.delete(entry).await
+        }
+
+        fn get_cache_capacity(&self) -> Option<CacheCapacity> {
+            self.disk.get_cache_capacity()
+        }
+    }
 
     async_test! {
         async fn test_default() {
@@ -257,14 +785,13 @@ mod tests {
             assert_eq!(cache.get("foo").await.unwrap(), foo_data.as_slice());
             assert_eq!(cache.get("bar").await.unwrap(), bar_data.as_slice());
 
-            match cache.put("baz", baz_data).await {
-                Err(err) => match err {
+            if let Err(err) = cache.put("baz", baz_data).await {
+                match err {
                     Error::LimitExceeded { limit_kind } => {
                         assert_eq!(limit_kind, LIMIT_KIND_BYTE);
                     }
                     _ => panic!("Unexpected error: {:?}", err),
-                },
-                _ => (),
+                }
             }
         }
 
@@ -278,14 +805,13 @@ mod tests {
             assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
             assert_eq!(cache.get("bar").await.unwrap(), b"bar".as_slice());
 
-            match cache.put("baz", b"baz".to_vec()).await {
-                Err(err) => match err {
+            if let Err(err) = cache.put("baz", b"baz".to_vec()).await {
+                match err {
                     Error::LimitExceeded { limit_kind } => {
                         assert_eq!(limit_kind, LIMIT_KIND_ENTRY);
                     }
                     _ => panic!("Unexpected error: {:?}", err),
-                },
-                _ => (),
+                }
             }
         }
 
@@ -313,5 +839,717 @@ mod tests {
                 assert_eq!(cache.strategy().current_entry_count, 2);
             }
         }
+
+        async fn test_verify() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+            cache.put("baz", b"baz".to_vec()).await.unwrap();
+
+            assert!(cache.verify().await.unwrap().is_empty());
+
+            // Truncate one entry and remove another
+            std::fs::write(temp_dir.as_ref().join("bar"), b"b").unwrap();
+            std::fs::remove_file(temp_dir.as_ref().join("baz")).unwrap();
+
+            let mut corrupted_keys = cache.verify().await.unwrap();
+            corrupted_keys.sort();
+            assert_eq!(corrupted_keys, vec!["bar", "baz"]);
+        }
+
+        async fn test_recovery_filtered() {
+            let temp_dir = TempDir::new();
+
+            // populate cache
+            {
+                let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+
+                cache.put("foo", b"foo".to_vec()).await.unwrap();
+                cache.put("bar", b"bar".to_vec()).await.unwrap();
+            }
+
+            // add a foreign file
+            std::fs::write(temp_dir.as_ref().join("foreign.txt"), b"foreign").unwrap();
+
+            // recover cache
+            {
+                let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+                let recovered_items = cache
+                    .recover_filtered(|k| (k == "foo").then(|| k.to_string()), |k| !k.ends_with(".txt"))
+                    .await
+                    .expect("Failed to recover");
+
+                assert_eq!(recovered_items, 1);
+                assert_eq!(cache.strategy().current_byte_count, 3);
+                assert_eq!(cache.strategy().current_entry_count, 1);
+
+                // The foreign file is left untouched, the unrecoverable one is moved
+                assert!(temp_dir.as_ref().join("foreign.txt").is_file());
+                assert!(temp_dir.as_ref().join("lost+found").join("bar").is_file());
+            }
+        }
+
+        async fn test_migrate_to() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            let cache = cache.migrate_to(Disk::new(temp_dir.as_ref(), Some(1024), None)).await.unwrap();
+            assert_eq!(cache.capacity().unwrap().used(), 6);
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+            assert_eq!(cache.get("bar").await.unwrap(), b"bar".as_slice());
+            let files = std::fs::read_dir(temp_dir.as_ref()).unwrap().filter(|entry| entry.as_ref().unwrap().path().is_file());
+            assert_eq!(files.count(), 2);
+        }
+
+        async fn test_migrate_to_rolls_back() {
+            let temp_dir = TempDir::new();
+            let source_dir = temp_dir.as_ref().join("source");
+            let dest_dir = temp_dir.as_ref().join("dest");
+            {
+                let mut cache = Cache::new(Disk::new(source_dir.as_path(), None, None), NO_COMPRESSION).await.unwrap();
+                cache.put("foo".to_string(), b"foo".to_vec()).await.unwrap();
+                cache.put("bar".to_string(), b"bar".to_vec()).await.unwrap();
+
+                // Only one entry fits the new strategy
+                let result = cache.migrate_to(Disk::new(dest_dir.as_path(), Some(4), None)).await;
+                assert!(matches!(result, Err(Error::LimitExceeded { .. })));
+            }
+
+            let files = std::fs::read_dir(&dest_dir).unwrap().filter(|entry| entry.as_ref().unwrap().path().is_file());
+            assert_eq!(files.count(), 0);
+
+            let mut cache = Cache::new(Disk::new(source_dir.as_path(), None, None), NO_COMPRESSION).await.unwrap();
+            assert_eq!(cache.recover(|key| Some(key.to_string())).await.unwrap(), 2);
+            assert_eq!(cache.get("foo".to_string()).await.unwrap(), b"foo".as_slice());
+            assert_eq!(cache.get("bar".to_string()).await.unwrap(), b"bar".as_slice());
+        }
+
+        async fn test_get_many_owned() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            let values = cache.get_many_owned(&["bar", "baz", "foo"]).await.unwrap();
+            assert_eq!(values, vec![Some(b"bar".to_vec()), None, Some(b"foo".to_vec())]);
+        }
+
+        async fn test_put_raw_key() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+
+            cache.put_raw_key("foo", "bar".to_string(), b"foo".to_vec()).await.unwrap();
+            assert!(temp_dir.as_ref().join("bar").is_file());
+            assert!(!temp_dir.as_ref().join("foo").exists());
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+        }
+
+        async fn test_hex_key_encoding() {
+            let temp_dir = TempDir::new();
+            let strategy = Disk::new(temp_dir.as_ref(), None, None).with_key_encoding(KeyEncoding::Hex);
+            let mut cache = Cache::new(strategy, NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo/bar", b"foo".to_vec()).await.unwrap();
+            assert!(temp_dir.as_ref().join("666f6f2f626172").is_file());
+            assert_eq!(cache.get("foo/bar").await.unwrap(), b"foo".as_slice());
+        }
+
+        async fn test_setup_failed() {
+            let temp_dir = TempDir::new();
+            let file_path = temp_dir.as_ref().join("file");
+            std::fs::write(&file_path, b"foo").unwrap();
+
+            let cache_dir = file_path.join("cache");
+            let result = Cache::<&str, _, _>::new(Disk::new(cache_dir.as_path(), None, None), NO_COMPRESSION).await;
+            match result {
+                Err(Error::SetupFailed { path, .. }) => assert_eq!(path, cache_dir),
+                other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+            }
+        }
+
+        async fn test_recover_lost_found() {
+            let temp_dir = TempDir::new();
+
+            // populate cache
+            {
+                let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+
+                cache.put("foo", b"foo".to_vec()).await.unwrap();
+                cache.put("bar", b"bar".to_vec()).await.unwrap();
+            }
+
+            // recover cache with faulty key recovery
+            {
+                let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+                let recovered_items = cache.recover(|_| None::<String>).await.unwrap();
+                assert_eq!(recovered_items, 0);
+                assert!(temp_dir.as_ref().join("lost+found").join("foo").is_file());
+            }
+
+            // recover lost+found with fixed key recovery
+            {
+                let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+                let recovered_items = cache
+                    .recover_lost_found(|k| (k == "foo").then(|| k.to_string()))
+                    .await
+                    .unwrap();
+
+                assert_eq!(recovered_items, 1);
+                assert_eq!(cache.strategy().current_byte_count, 3);
+                assert_eq!(cache.get("foo".to_string()).await.unwrap(), b"foo".as_slice());
+                assert!(temp_dir.as_ref().join("foo").is_file());
+                assert!(temp_dir.as_ref().join("lost+found").join("bar").is_file());
+            }
+        }
+
+        async fn test_archive_taken_to() {
+            let temp_dir = TempDir::new();
+            let archive_dir = temp_dir.as_ref().join("archive");
+            let strategy = Disk::new(temp_dir.as_ref(), Some(1024), None).archive_taken_to(archive_dir.as_path());
+            let mut cache = Cache::new(strategy, NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            assert_eq!(cache.take("foo").await.unwrap(), b"foo".to_vec());
+            cache.delete("bar").await.unwrap();
+
+            assert_eq!(cache.capacity().unwrap().used(), 0);
+            assert!(!temp_dir.as_ref().join("foo").exists());
+            assert_eq!(read_entry_file(archive_dir.join("foo")), b"foo");
+            assert_eq!(read_entry_file(archive_dir.join("bar")), b"bar");
+        }
+
+        async fn test_drain() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), Some(1024), None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            let mut entries = cache.drain().await.unwrap();
+            entries.sort();
+            assert_eq!(entries, vec![("bar", b"bar".to_vec()), ("foo", b"foo".to_vec())]);
+
+            assert!(!cache.exists("foo"));
+            assert_eq!(cache.capacity().unwrap().used(), 0);
+            assert!(!temp_dir.as_ref().join("foo").exists());
+        }
+
+        async fn test_get_map() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            let values = cache.get_map(["foo", "baz"]).await.unwrap();
+            assert_eq!(values.len(), 1);
+            assert_eq!(values["foo"], b"foo".to_vec());
+        }
+
+        async fn test_swap() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), Some(1024), None), NO_COMPRESSION).await.unwrap();
+
+            assert_eq!(cache.swap("foo", b"foo".to_vec()).await.unwrap(), None);
+            assert_eq!(cache.swap("foo", b"bar".to_vec()).await.unwrap(), Some(b"foo".to_vec()));
+            assert_eq!(cache.get("foo").await.unwrap(), b"bar".as_slice());
+            assert_eq!(cache.capacity().unwrap().used(), 3);
+        }
+
+        async fn test_temp_dir() {
+            let temp_dir = TempDir::new();
+            let cache_dir = temp_dir.as_ref().join("cache");
+            let scratch_dir = temp_dir.as_ref().join("scratch");
+            let strategy = Disk::new(cache_dir.as_path(), None, None).with_temp_dir(scratch_dir.as_path());
+            let mut cache = Cache::new(strategy, NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("foo", b"bar".to_vec()).await.unwrap();
+
+            assert_eq!(read_entry_file(cache_dir.join("foo")), b"bar");
+            assert_eq!(std::fs::read_dir(&scratch_dir).unwrap().count(), 0);
+            assert_eq!(cache.get("foo").await.unwrap(), b"bar".as_slice());
+        }
+
+        async fn test_is_persistent() {
+            let temp_dir = TempDir::new();
+            let cache = Cache::<&str, _, _>::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            assert!(cache.is_persistent());
+        }
+
+        async fn test_take_many() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+            cache.put("qux", b"qux".to_vec()).await.unwrap();
+
+            let values = cache.take_many(&["bar", "baz", "foo"]).await.unwrap();
+            assert_eq!(values, vec![Some(b"bar".to_vec()), None, Some(b"foo".to_vec())]);
+
+            assert!(!temp_dir.as_ref().join("foo").exists());
+            assert!(!temp_dir.as_ref().join("bar").exists());
+            assert!(cache.exists("qux"));
+        }
+
+        async fn test_recover_already_indexed() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), Some(1024), None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo".to_string(), b"foo".to_vec()).await.unwrap();
+
+            let recovered_items = cache.recover(|k| Some(k.to_string())).await.unwrap();
+            assert_eq!(recovered_items, 0);
+            assert_eq!(cache.capacity().unwrap().used(), 3);
+            assert_eq!(cache.get("foo".to_string()).await.unwrap(), b"foo".as_slice());
+        }
+
+        async fn test_close() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo".to_string(), b"foo".to_vec()).await.unwrap();
+            cache.close().await.unwrap();
+
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            assert_eq!(cache.recover(|k| Some(k.to_string())).await.unwrap(), 1);
+            assert_eq!(cache.get("foo".to_string()).await.unwrap(), b"foo".as_slice());
+        }
+
+        async fn test_copy_into() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            let mut dest = Cache::new(Disk::new(temp_dir.as_ref(), Some(1024), None), NO_COMPRESSION).await.unwrap();
+            assert_eq!(cache.copy_into(&mut dest).await.unwrap(), 2);
+
+            assert_eq!(dest.capacity().unwrap().used(), 6);
+            assert_eq!(dest.get("foo").await.unwrap(), b"foo".as_slice());
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+            let files = std::fs::read_dir(temp_dir.as_ref()).unwrap().filter(|entry| entry.as_ref().unwrap().path().is_file());
+            assert_eq!(files.count(), 2);
+        }
+
+        async fn test_key_too_long() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), Some(1024), None), NO_COMPRESSION).await.unwrap();
+
+            let key = "k".repeat(256);
+            let result = cache.put(key.clone(), b"foo".to_vec()).await;
+            assert!(matches!(result, Err(Error::KeyTooLong { len: 256, limit: 255 })));
+            assert!(!cache.exists(key));
+            assert_eq!(cache.capacity().unwrap().used(), 0);
+
+            cache.put("k".repeat(255), b"foo".to_vec()).await.unwrap();
+        }
+
+        async fn test_resync() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), Some(1024), None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo".to_string(), b"foo".to_vec()).await.unwrap();
+            cache.put("bar".to_string(), b"bar".to_vec()).await.unwrap();
+
+            // Mutate the cache directory externally
+            std::fs::remove_file(temp_dir.as_ref().join("bar")).unwrap();
+            std::fs::write(temp_dir.as_ref().join("baz"), b"bazbaz").unwrap();
+
+            let recovered_items = cache.resync(|k| Some(k.to_string())).await.unwrap();
+            assert_eq!(recovered_items, 2);
+            assert_eq!(cache.capacity().unwrap().used(), 9);
+            assert!(!cache.exists("bar".to_string()));
+            assert_eq!(cache.get("baz".to_string()).await.unwrap(), b"bazbaz".as_slice());
+        }
+
+        async fn test_put_transaction() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), Some(12), None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+
+            let entries = vec![("bar", b"bar".to_vec()), ("baz", b"baz".to_vec())];
+            cache.put_transaction(entries).await.unwrap();
+            assert_eq!(cache.capacity().unwrap().used(), 9);
+
+            // The last entry doesn't fit, so the batch is rolled back
+            let entries = vec![
+                ("foo", b"new".to_vec()),
+                ("qux", b"qux".to_vec()),
+                ("quux", b"quux".to_vec()),
+            ];
+            let result = cache.put_transaction(entries).await;
+            assert!(matches!(result, Err(Error::LimitExceeded { .. })));
+
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+            assert!(!cache.exists("qux"));
+            assert!(!temp_dir.as_ref().join("qux").exists());
+            assert_eq!(cache.capacity().unwrap().used(), 9);
+        }
+
+        async fn test_disk_usage() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            assert_eq!(cache.disk_usage(false).unwrap(), 0);
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            let usage = cache.disk_usage(false).unwrap();
+            assert!(usage >= 3);
+
+            // Files in `lost+found` are only counted when asked for
+            std::fs::create_dir_all(temp_dir.as_ref().join("lost+found")).unwrap();
+            std::fs::write(temp_dir.as_ref().join("lost+found").join("bar"), b"bar").unwrap();
+            assert_eq!(cache.disk_usage(false).unwrap(), usage);
+            assert!(cache.disk_usage(true).unwrap() > usage);
+        }
+
+        async fn test_put_cancelled() {
+            let temp_dir = TempDir::new();
+            let strategy = StalledDisk {
+                disk: Disk::new(temp_dir.as_ref(), None, None),
+                stalled: false,
+            };
+            let mut cache = Cache::new(strategy, NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"old".to_vec()).await.unwrap();
+
+            // Drop an overwrite while it's stalled
+            cache.strategy_mut().stalled = true;
+            {
+                let mut put = Box::pin(cache.put("foo", b"new".to_vec()));
+                assert!(futures_util::poll!(&mut put).is_pending());
+            }
+
+            // The old value survives the cancelled overwrite
+            assert_eq!(cache.get("foo").await.unwrap(), b"old".as_slice());
+            assert_eq!(cache.strategy().disk.current_byte_count, 3);
+            assert_eq!(cache.strategy().disk.current_entry_count, 1);
+
+            // The retried value is kept, and no other file is left behind
+            cache.strategy_mut().stalled = false;
+            cache.put("foo", b"new".to_vec()).await.unwrap();
+            assert_eq!(cache.get("foo").await.unwrap(), b"new".as_slice());
+            assert_eq!(read_entry_file(temp_dir.as_ref().join("foo")), b"new");
+            assert_eq!(std::fs::read_dir(temp_dir.as_ref()).unwrap().count(), 2);
+            assert_eq!(std::fs::read_dir(temp_dir.as_ref().join(".tmp")).unwrap().count(), 0);
+            assert_eq!(cache.strategy().disk.current_byte_count, 3);
+            assert_eq!(cache.strategy().disk.current_entry_count, 1);
+        }
+
+        async fn test_failed_overwrite_keeps_value() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), Some(3), None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+
+            // The old entry's space counts as free
+            cache.put("foo", b"bar".to_vec()).await.unwrap();
+
+            let result = cache.put("foo", b"foobar".to_vec()).await;
+            assert!(matches!(result, Err(Error::LimitExceeded { .. })));
+            assert_eq!(cache.get("foo").await.unwrap(), b"bar".as_slice());
+            assert_eq!(cache.strategy().current_byte_count, 3);
+            assert_eq!(cache.strategy().current_entry_count, 1);
+        }
+
+        async fn test_into_parts() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+
+            let (strategy, compressor) = cache.into_parts();
+            assert!(compressor.is_none());
+            assert_eq!(strategy.current_byte_count, 3);
+            assert_eq!(strategy.current_entry_count, 1);
+
+            let cache = Cache::<&str, _, _>::new(strategy, NO_COMPRESSION).await.unwrap();
+            assert_eq!(cache.strategy().current_entry_count, 1);
+            assert!(temp_dir.as_ref().join("foo").is_file());
+        }
+
+        async fn test_append() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            cache.append_or_create("foo", b"foo").await.unwrap();
+            cache.append_or_create("foo", b"bar").await.unwrap();
+
+            assert_eq!(cache.get("foo").await.unwrap(), b"foobar".as_slice());
+            assert_eq!(read_entry_file(temp_dir.as_ref().join("foo")), b"foobar");
+            assert_eq!(cache.strategy().current_byte_count, 6);
+            assert_eq!(cache.verify().await.unwrap(), Vec::<&str>::new());
+        }
+
+        async fn test_snapshot() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            let mut snapshot = cache.snapshot().await.unwrap();
+            snapshot.sort();
+            assert_eq!(snapshot, vec![("bar", b"bar".to_vec()), ("foo", b"foo".to_vec())]);
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+            assert_eq!(cache.strategy().current_entry_count, 2);
+        }
+
+        async fn test_strict_reads() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(
+                Disk::new(temp_dir.as_ref(), None, None).with_strict_reads(true),
+                NO_COMPRESSION,
+            ).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+            cache.put("baz", b"baz".to_vec()).await.unwrap();
+
+            // Truncate one file and delete another
+            std::fs::write(temp_dir.as_ref().join("foo"), b"fo").unwrap();
+            std::fs::remove_file(temp_dir.as_ref().join("bar")).unwrap();
+
+            assert!(matches!(
+                cache.get("foo").await,
+                Err(crate::Error::IntegrityError { expected: 3, actual: 2, .. })
+            ));
+
+            let mut removed = cache.remove_corrupted().await.unwrap();
+            removed.sort();
+            assert_eq!(removed, vec!["bar", "foo"]);
+            assert_eq!(cache.get("baz").await.unwrap(), b"baz".as_slice());
+            assert_eq!(cache.strategy().current_entry_count, 1);
+            assert_eq!(cache.strategy().current_byte_count, 3);
+        }
+
+        async fn test_strict_take() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(
+                Disk::new(temp_dir.as_ref(), None, None).with_strict_reads(true),
+                NO_COMPRESSION,
+            ).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            std::fs::write(temp_dir.as_ref().join("foo"), b"fooo").unwrap();
+
+            assert!(matches!(
+                cache.take("foo").await,
+                Err(crate::Error::IntegrityError { expected: 3, actual: 4, .. })
+            ));
+            assert!(!temp_dir.as_ref().join("foo").exists());
+            assert_eq!(cache.strategy().current_entry_count, 0);
+            assert_eq!(cache.strategy().current_byte_count, 0);
+        }
+
+        async fn test_zero_length() {
+            let temp_dir = TempDir::new();
+
+            // populate cache
+            {
+                let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+                cache.put("empty", Vec::new()).await.unwrap();
+
+                assert!(cache.exists("empty"));
+                assert!(cache.get("empty").await.unwrap().is_empty());
+                assert_eq!(cache.strategy().current_byte_count, 0);
+                assert_eq!(cache.strategy().current_entry_count, 1);
+            }
+
+            // recover cache
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            assert_eq!(cache.recover(|k| Some(k.to_string())).await.unwrap(), 1);
+            assert!(cache.try_get("empty".to_string()).await.unwrap().unwrap().is_empty());
+
+            cache.delete("empty".to_string()).await.unwrap();
+            assert_eq!(cache.strategy().current_byte_count, 0);
+            assert_eq!(cache.strategy().current_entry_count, 0);
+        }
+
+        async fn test_recovery_fast() {
+            let temp_dir = TempDir::new();
+
+            // populate cache
+            {
+                let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+                cache.put("foo", b"foo".to_vec()).await.unwrap();
+                cache.put("barbaz", b"barbaz".to_vec()).await.unwrap();
+                cache.put("empty", Vec::new()).await.unwrap();
+            }
+
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            assert_eq!(cache.recover(|k| Some(k.to_string())).await.unwrap(), 3);
+            let byte_count = cache.strategy().current_byte_count;
+
+            let mut fast_cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            assert_eq!(fast_cache.recover_fast(|k| Some(k.to_string())).await.unwrap(), 3);
+            assert_eq!(fast_cache.strategy().current_byte_count, byte_count);
+            assert_eq!(fast_cache.strategy().current_byte_count, 9);
+            assert_eq!(fast_cache.strategy().current_entry_count, 3);
+            assert_eq!(fast_cache.get("barbaz".to_string()).await.unwrap(), b"barbaz".as_slice());
+        }
+
+        async fn test_entry_overhead() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(
+                Disk::new(temp_dir.as_ref(), Some(30), None).with_entry_overhead(10),
+                NO_COMPRESSION,
+            ).await.unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+            assert_eq!(cache.capacity().unwrap().used(), 26);
+            assert_eq!(cache.strategy().current_byte_count, 6);
+
+            let result = cache.put("baz", b"baz".to_vec()).await;
+            assert!(matches!(result, Err(Error::LimitExceeded { limit_kind }) if limit_kind == LIMIT_KIND_BYTE));
+
+            cache.delete("bar").await.unwrap();
+            assert_eq!(cache.capacity().unwrap().used(), 13);
+            cache.put("baz", b"baz".to_vec()).await.unwrap();
+        }
+
+        async fn test_compact() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            std::fs::remove_file(temp_dir.as_ref().join("bar")).unwrap();
+            std::fs::create_dir_all(temp_dir.as_ref().join("empty").join("nested")).unwrap();
+            std::fs::create_dir_all(temp_dir.as_ref().join("lost+found")).unwrap();
+            std::fs::write(temp_dir.as_ref().join("lost+found").join("old"), b"old").unwrap();
+            std::fs::write(temp_dir.as_ref().join(".tmp").join("bincache-1-0.tmp"), b"partial").unwrap();
+            std::fs::write(temp_dir.as_ref().join(".tmp").join("foreign.tmp"), b"foreign").unwrap();
+            cache.strategy_mut().current_byte_count = 100;
+
+            let report = cache.compact(Duration::from_secs(3600)).await.unwrap();
+            assert_eq!(report.removed_empty_dirs(), 2);
+            assert_eq!(report.removed_lost_found(), 0);
+            assert_eq!(report.removed_temp_files(), 0);
+            assert!(temp_dir.as_ref().join(".tmp").is_dir());
+            assert!(temp_dir.as_ref().join("lost+found").is_dir());
+
+            // Counters are recomputed from the index, which keeps corrupted entries
+            assert_eq!(cache.strategy().current_byte_count, 6);
+            assert_eq!(cache.strategy().current_entry_count, 2);
+            assert!(cache.exists("bar"));
+
+            std::thread::sleep(Duration::from_millis(10));
+            let report = cache.compact(Duration::ZERO).await.unwrap();
+            assert_eq!(report.removed_empty_dirs(), 0);
+            assert_eq!(report.removed_lost_found(), 1);
+            assert_eq!(report.removed_temp_files(), 1);
+            assert!(!temp_dir.as_ref().join("lost+found").join("old").exists());
+            assert!(temp_dir.as_ref().join(".tmp").join("foreign.tmp").exists());
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+        }
+
+        async fn test_read_buffer() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(
+                Disk::new(temp_dir.as_ref(), None, None).with_read_buffer(1),
+                NO_COMPRESSION,
+            ).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            // The first read is a miss, the following ones are served from memory
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+            std::fs::write(temp_dir.as_ref().join("foo"), b"xxx").unwrap();
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+            assert_eq!(cache.get_shared("foo").await.unwrap().as_ref(), b"foo");
+
+            // Reading another entry evicts the least recently read one
+            assert_eq!(cache.get("bar").await.unwrap(), b"bar".as_slice());
+            assert_eq!(cache.get("foo").await.unwrap(), b"xxx".as_slice());
+
+            // Changed entries aren't served from the buffer
+            cache.put("foo", b"new".to_vec()).await.unwrap();
+            assert_eq!(cache.get("foo").await.unwrap(), b"new".as_slice());
+
+            let stats = cache.read_buffer_stats().unwrap();
+            assert_eq!(stats.hits(), 2);
+            assert_eq!(stats.misses(), 4);
+            assert!((stats.hit_rate() - 2.0 / 6.0).abs() < f64::EPSILON);
+        }
+
+        async fn test_temp_dir_is_cache_dir() {
+            let temp_dir = TempDir::new();
+            let strategy = Disk::new(temp_dir.as_ref(), None, None).with_temp_dir(temp_dir.as_ref());
+            let result = Cache::<&str, _, _>::new(strategy, NO_COMPRESSION).await;
+            assert!(matches!(result, Err(Error::SetupFailed { .. })));
+        }
+
+        async fn test_entry_header() {
+            let temp_dir = TempDir::new();
+
+            // Files written before headers existed are recovered as-is
+            std::fs::write(temp_dir.as_ref().join("legacy"), b"legacy").unwrap();
+            {
+                let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+                cache.put("foo", b"foo".to_vec()).await.unwrap();
+            }
+
+            let data = std::fs::read(temp_dir.as_ref().join("foo")).unwrap();
+            let (header, header_len) = EntryHeader::decode(&data).unwrap();
+            assert_eq!(header.original_len, 3);
+            assert!(!header.compressed);
+            assert_eq!(&data[header_len..], b"foo");
+
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            cache.recover(|key| Some(key.to_string())).await.unwrap();
+            assert_eq!(cache.get("foo".to_string()).await.unwrap(), b"foo".as_slice());
+            assert_eq!(cache.get("legacy".to_string()).await.unwrap(), b"legacy".as_slice());
+            assert!(cache.verify().await.unwrap().is_empty());
+            assert_eq!(cache.strategy().current_byte_count, 9);
+        }
+    }
+
+    #[cfg(unix)]
+    async_test! {
+        async fn test_recovery_with_report() {
+            let temp_dir = TempDir::new();
+
+            // populate cache
+            {
+                let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+                cache.put("foo", b"foo".to_vec()).await.unwrap();
+                cache.put("bar", b"bar".to_vec()).await.unwrap();
+                cache.put("unknown", b"unknown".to_vec()).await.unwrap();
+            }
+
+            // add an entry that can't be read
+            std::os::unix::fs::symlink(temp_dir.as_ref().join("missing"), temp_dir.as_ref().join("dangling")).unwrap();
+
+            let mut cache = Cache::new(Disk::new(temp_dir.as_ref(), None, None), NO_COMPRESSION).await.unwrap();
+            let report = cache
+                .recover_with_report(|k| (k != "unknown").then(|| k.to_string()))
+                .await
+                .unwrap();
+
+            assert_eq!(report.recovered(), 2);
+            assert_eq!(report.relocated_to_lost_found(), 1);
+            assert_eq!(report.read_errors(), 1);
+            assert!(temp_dir.as_ref().join("lost+found").join("unknown").exists());
+            assert_eq!(cache.strategy().current_byte_count, 6);
+            assert_eq!(cache.strategy().current_entry_count, 2);
+        }
+
+        async fn test_permissions() {
+            use std::os::unix::fs::PermissionsExt;
+
+            let temp_dir = TempDir::new();
+            let cache_dir = temp_dir.as_ref().join("cache");
+            let mut cache = Cache::new(
+                Disk::new(cache_dir.as_path(), None, None)
+                    .with_dir_mode(0o700)
+                    .with_file_mode(0o600),
+                NO_COMPRESSION,
+            ).await.unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+
+            let dir_mode = std::fs::metadata(&cache_dir).unwrap().permissions().mode();
+            let file_mode = std::fs::metadata(cache_dir.join("foo")).unwrap().permissions().mode();
+
+            assert_eq!(dir_mode & 0o777, 0o700);
+            assert_eq!(file_mode & 0o777, 0o600);
+        }
     }
 }
@@ -5,10 +5,15 @@ use std::{
 };
 
 use crate::{
-    traits::{CacheKey, CacheStrategy, FlushableStrategy, RecoverableStrategy},
-    CacheCapacity, DiskUtil, Result,
+    entry_header::EntryHeader,
+    traits::{
+        CacheKey, CacheStrategy, CompressionStrategy, EntryLocation, FlushableStrategy,
+        KeyEncoding, RecoverableStrategy, Tier,
+    },
+    CacheCapacity, DiskUtil, RecoveryReport, Result,
 };
 
+const LIMIT_KIND_BYTE_MEMORY: &str = "Stored bytes in memory";
 const LIMIT_KIND_BYTE_DISK: &str = "Stored bytes on disk";
 const LIMIT_KIND_ENTRY_DISK: &str = "Stored entries on disk";
 
@@ -38,6 +43,10 @@ impl LimitEvaluation {
 pub struct MemoryEntry {
     data: Vec<u8>,
     byte_len: usize,
+    /// The header written along with the data when it's flushed to disk.
+    header: EntryHeader,
+    /// The copy written by a [copying flush](crate::FlushMode::Copy), if any.
+    disk_copy: Option<DiskEntry>,
 }
 
 /// A cache entry stored on disk.
@@ -45,6 +54,20 @@ pub struct MemoryEntry {
 pub struct DiskEntry {
     path: PathBuf,
     byte_len: usize,
+    /// The header in front of the data, unless the file was written without one.
+    header: Option<EntryHeader>,
+}
+
+impl DiskEntry {
+    /// Get the number of bytes the header occupies in front of the data.
+    fn header_len(&self) -> usize {
+        self.header.map_or(0, |header| header.encoded_len())
+    }
+
+    /// Whether the data was compressed with a disk compressor.
+    fn is_disk_compressed(&self) -> bool {
+        self.header.map_or(false, |header| header.disk_compressed)
+    }
 }
 
 /// A hybrid cache entry.
@@ -54,6 +77,20 @@ pub enum Entry {
     Disk(DiskEntry),
 }
 
+impl Entry {
+    /// Get the path of the entry's file, if it has one.
+    fn file_path(&self) -> Option<&Path> {
+        match self {
+            Entry::Memory(MemoryEntry {
+                disk_copy: Some(entry),
+                ..
+            })
+            | Entry::Disk(entry) => Some(&entry.path),
+            Entry::Memory(_) => None,
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Limits {
     /// The maximum number of bytes that can be stored.
@@ -76,23 +113,71 @@ impl Limits {
     }
 
     fn evaluate(&self, size: usize) -> LimitEvaluation {
+        self.evaluate_replacing(size, None)
+    }
+
+    /// Evaluate a size against the limits, as if an entry of `replaced` bytes was removed.
+    fn evaluate_replacing(&self, size: usize, replaced: Option<usize>) -> LimitEvaluation {
+        let (byte_count, entry_count) = match replaced {
+            Some(byte_len) => (
+                self.current_byte_count.saturating_sub(byte_len),
+                self.current_entry_count.saturating_sub(1),
+            ),
+            None => (self.current_byte_count, self.current_entry_count),
+        };
         if let Some(byte_limit) = self.byte_limit {
-            if self.current_byte_count + size > byte_limit {
+            if byte_count.saturating_add(size) > byte_limit {
                 return LimitEvaluation::LimitExceeded(LimitExceededKind::Bytes);
             }
-        } else if let Some(entries_limit) = self.entry_limit {
-            if self.current_entry_count + 1 > entries_limit {
+        }
+        if let Some(entries_limit) = self.entry_limit {
+            if entry_count.saturating_add(1) > entries_limit {
                 return LimitEvaluation::LimitExceeded(LimitExceededKind::Entries);
             }
         }
         LimitEvaluation::LimitSatisfied
     }
+
+    /// Check whether an entry can grow by `size` bytes.
+    fn fits_growth(&self, size: usize) -> bool {
+        self.byte_limit.map_or(true, |byte_limit| {
+            self.current_byte_count.saturating_add(size) <= byte_limit
+        })
+    }
+
+    /// Increment the byte counter for an entry that grew by `size` bytes.
+    fn grow(&mut self, size: usize) {
+        self.current_byte_count = self.current_byte_count.saturating_add(size);
+    }
+
+    /// Reset the limit counters, as if no entries were stored.
+    fn reset(&mut self) {
+        self.current_byte_count = 0;
+        self.current_entry_count = 0;
+    }
+
+    /// Increment the limit counters for a newly stored entry.
+    fn increment(&mut self, byte_len: usize) {
+        self.current_byte_count = self.current_byte_count.saturating_add(byte_len);
+        self.current_entry_count = self.current_entry_count.saturating_add(1);
+    }
+
+    /// Decrement the limit counters for a removed entry.
+    fn decrement(&mut self, byte_len: usize) {
+        debug_assert!(self.current_byte_count >= byte_len, "byte count underflow");
+        debug_assert!(self.current_entry_count >= 1, "entry count underflow");
+        self.current_byte_count = self.current_byte_count.saturating_sub(byte_len);
+        self.current_entry_count = self.current_entry_count.saturating_sub(1);
+    }
 }
 
 /// Hybrid cache strategy.
 ///
 /// This strategy stores entries on memory and flushed entries to disk if memory doesn't suffice.
 /// It can be configured to limit the number of bytes and/or entries that can be stored.
+///
+/// Entries written to disk can optionally be compressed by the strategy itself,
+/// see [Hybrid::with_disk_compression].
 #[derive(Debug)]
 pub struct Hybrid {
     /// The directory where entries are stored.
@@ -101,6 +186,18 @@ pub struct Hybrid {
     memory_limits: Limits,
     /// Disk usage limits.
     disk_limits: Limits,
+    /// The permissions of the cache directory (Unix only).
+    dir_mode: Option<u32>,
+    /// The permissions of created entry files (Unix only).
+    file_mode: Option<u32>,
+    /// How keys are encoded as file names.
+    key_encoding: KeyEncoding,
+    /// The directory taken and deleted entry files are moved to, instead of being removed.
+    archive_dir: Option<PathBuf>,
+    /// The directory entry files are written to before being moved into place.
+    temp_dir: Option<PathBuf>,
+    /// The compressor used for entries stored on disk.
+    disk_compressor: Option<Box<dyn CompressionStrategy + Send + Sync>>,
 }
 
 impl Default for Hybrid {
@@ -109,6 +206,12 @@ impl Default for Hybrid {
             cache_dir: PathBuf::from("cache"),
             memory_limits: Limits::default(),
             disk_limits: Limits::default(),
+            dir_mode: None,
+            file_mode: None,
+            key_encoding: KeyEncoding::default(),
+            archive_dir: None,
+            temp_dir: None,
+            disk_compressor: None,
         }
     }
 }
@@ -123,8 +226,114 @@ impl Hybrid {
             cache_dir: cache_dir.into().into_owned(),
             memory_limits,
             disk_limits,
+            dir_mode: None,
+            file_mode: None,
+            key_encoding: KeyEncoding::default(),
+            archive_dir: None,
+            temp_dir: None,
+            disk_compressor: None,
         }
     }
+
+    /// Set the directory where entries are stored on disk.
+    pub fn with_cache_dir<'a>(mut self, cache_dir: impl Into<Cow<'a, Path>>) -> Self {
+        self.cache_dir = cache_dir.into().into_owned();
+        self
+    }
+
+    /// Set the maximum number of bytes that can be stored in memory.
+    pub fn with_memory_byte_limit(mut self, byte_limit: usize) -> Self {
+        self.memory_limits.byte_limit = Some(byte_limit);
+        self
+    }
+
+    /// Set the maximum number of entries that can be stored in memory.
+    pub fn with_memory_entry_limit(mut self, entry_limit: usize) -> Self {
+        self.memory_limits.entry_limit = Some(entry_limit);
+        self
+    }
+
+    /// Set the maximum number of bytes that can be stored on disk.
+    pub fn with_disk_byte_limit(mut self, byte_limit: usize) -> Self {
+        self.disk_limits.byte_limit = Some(byte_limit);
+        self
+    }
+
+    /// Set the maximum number of entries that can be stored on disk.
+    pub fn with_disk_entry_limit(mut self, entry_limit: usize) -> Self {
+        self.disk_limits.entry_limit = Some(entry_limit);
+        self
+    }
+
+    /// Set the permissions of the cache directory, e.g. `0o700`.
+    ///
+    /// This has no effect on non-Unix platforms.
+    pub fn with_dir_mode(mut self, mode: u32) -> Self {
+        self.dir_mode = Some(mode);
+        self
+    }
+
+    /// Set the permissions of created entry files, e.g. `0o600`.
+    ///
+    /// This has no effect on non-Unix platforms.
+    pub fn with_file_mode(mut self, mode: u32) -> Self {
+        self.file_mode = Some(mode);
+        self
+    }
+
+    /// Set how keys are encoded as file names.
+    ///
+    /// Recovered entries are identified by their file name, so the key closure
+    /// passed to recovery receives the encoded key.
+    pub fn with_key_encoding(mut self, key_encoding: KeyEncoding) -> Self {
+        self.key_encoding = key_encoding;
+        self
+    }
+
+    /// Move the files of taken and deleted entries into `archive_dir` instead of removing them.
+    ///
+    /// Files are moved by renaming them, so `archive_dir` should be on the same
+    /// file system as the cache directory. Archived files with the same name are replaced.
+    pub fn archive_taken_to<'a>(mut self, archive_dir: impl Into<Cow<'a, Path>>) -> Self {
+        self.archive_dir = Some(archive_dir.into().into_owned());
+        self
+    }
+
+    /// Write entry files to `temp_dir` first, then move them into the cache directory.
+    ///
+    /// Entry files are always written to a temporary file first, so readers never
+    /// see partially written entries. By default, that file is created in a `.tmp`
+    /// directory inside the cache directory. If `temp_dir` is on a different file
+    /// system, files are copied instead, which isn't atomic. `temp_dir` must not be
+    /// the cache directory, or setting up the cache fails.
+    ///
+    /// The copy is a blocking call, so with an async runtime it holds an executor
+    /// thread for as long as copying the entry takes. Keep `temp_dir` on the cache
+    /// directory's file system when storing large entries.
+    pub fn with_temp_dir<'a>(mut self, temp_dir: impl Into<Cow<'a, Path>>) -> Self {
+        self.temp_dir = Some(temp_dir.into().into_owned());
+        self
+    }
+
+    /// Compress entries only when they are written to disk.
+    ///
+    /// Entries in memory stay uncompressed for fast reads, while entries on disk
+    /// are compressed on write and decompressed on read. Disk limits apply to the
+    /// compressed size.
+    ///
+    /// Use this instead of a [Cache](crate::Cache)-level compressor, otherwise
+    /// disk entries are compressed twice.
+    ///
+    /// Compressed files are marked in their header. Without a disk compressor, they
+    /// are skipped on recovery and left in place. Reading a file compressed with a
+    /// different compressor fails with [Error::DecompressionError](crate::Error::DecompressionError).
+    pub fn with_disk_compression<C>(mut self, compressor: C) -> Self
+    where
+        C: CompressionStrategy + Send + Sync + 'static,
+    {
+        self.disk_compressor = Some(Box::new(compressor));
+        self
+    }
 }
 
 #[async_trait]
@@ -132,7 +341,16 @@ impl CacheStrategy for Hybrid {
     type CacheEntry = Entry;
 
     async fn setup(&mut self) -> Result<()> {
-        DiskUtil::create_dir(&self.cache_dir).await
+        DiskUtil::setup_dir(&self.cache_dir, self.dir_mode).await?;
+        if let Some(archive_dir) = &self.archive_dir {
+            DiskUtil::setup_dir(archive_dir, self.dir_mode).await?;
+        }
+        let temp_dir = self
+            .temp_dir
+            .get_or_insert_with(|| self.cache_dir.join(DiskUtil::TEMP_DIR_NAME));
+        DiskUtil::setup_dir(&*temp_dir, self.dir_mode).await?;
+        DiskUtil::check_temp_dir(&self.cache_dir, temp_dir)?;
+        Ok(())
     }
 
     async fn put<'a, K, V>(&mut self, key: &K, value: V) -> Result<Self::CacheEntry>
@@ -141,76 +359,107 @@ impl CacheStrategy for Hybrid {
         V: Into<Cow<'a, [u8]>> + Send,
     {
         let value = value.into();
-        let byte_len = value.as_ref().len();
-
-        // Evaluate limits
-        let fits_into_memory = self.memory_limits.evaluate(byte_len);
-        let fits_into_disk = self.disk_limits.evaluate(byte_len);
-
-        // Try to store in memory
-        if fits_into_memory.is_satisfied() {
-            // Increment limits
-            self.memory_limits.current_byte_count += byte_len;
-            self.memory_limits.current_entry_count += 1;
+        let header = EntryHeader {
+            original_len: value.len() as u64,
+            ..Default::default()
+        };
+        self.store(key, value, &header, None).await
+    }
 
-            Ok(Entry::Memory(MemoryEntry {
-                data: value.into_owned(),
-                byte_len,
-            }))
-        }
-        // Try to store on disk
-        else if fits_into_disk.is_satisfied() {
-            // Write to disk
-            let path = self.cache_dir.join(key.to_key());
-            DiskUtil::write(&path, &value).await?;
+    async fn put_with_header<'a, K, V>(
+        &mut self,
+        key: &K,
+        value: V,
+        header: &EntryHeader,
+    ) -> Result<Self::CacheEntry>
+    where
+        K: CacheKey + Sync + Send,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        self.store(key, value.into(), header, None).await
+    }
 
-            // Increment limits
-            self.disk_limits.current_byte_count += byte_len;
-            self.disk_limits.current_entry_count += 1;
+    async fn replace<'a, K, V>(
+        &mut self,
+        key: &K,
+        old: &Self::CacheEntry,
+        value: V,
+    ) -> Result<Self::CacheEntry>
+    where
+        K: CacheKey + Sync + Send,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        let value = value.into();
+        let header = EntryHeader {
+            original_len: value.len() as u64,
+            ..Default::default()
+        };
+        self.store(key, value, &header, Some(old)).await
+    }
 
-            Ok(Entry::Disk(DiskEntry { path, byte_len }))
-        }
-        // Return limit exceeded error
-        else {
-            use LimitEvaluation::LimitExceeded;
-            let limit_kind = Cow::Borrowed(match fits_into_disk {
-                LimitExceeded(LimitExceededKind::Bytes) => LIMIT_KIND_BYTE_DISK,
-                LimitExceeded(LimitExceededKind::Entries) => LIMIT_KIND_ENTRY_DISK,
-                _ => unreachable!(),
-            });
-            Err(crate::Error::LimitExceeded { limit_kind })
-        }
+    async fn replace_with_header<'a, K, V>(
+        &mut self,
+        key: &K,
+        old: &Self::CacheEntry,
+        value: V,
+        header: &EntryHeader,
+    ) -> Result<Self::CacheEntry>
+    where
+        K: CacheKey + Sync + Send,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        self.store(key, value.into(), header, Some(old)).await
     }
 
     async fn get<'a>(&self, entry: &'a Self::CacheEntry) -> Result<Cow<'a, [u8]>> {
         match entry {
             Entry::Memory(entry) => Ok(Cow::Borrowed(&entry.data)),
-            Entry::Disk(entry) => Ok(Cow::Owned(
-                DiskUtil::read(&entry.path, Some(entry.byte_len)).await?,
-            )),
+            Entry::Disk(entry) => {
+                let (_, data) = DiskUtil::read(&entry.path, Some(entry.byte_len)).await?;
+                self.decompress_disk(entry, Cow::Owned(data)).await
+            }
         }
     }
 
     async fn take(&mut self, entry: Self::CacheEntry) -> Result<Vec<u8>> {
         match entry {
             Entry::Memory(entry) => {
+                if let Some(disk_copy) = entry.disk_copy {
+                    self.delete_disk_entry(disk_copy).await?;
+                }
+
                 // Decrement limits
-                self.memory_limits.current_byte_count -= entry.byte_len;
-                self.memory_limits.current_entry_count -= 1;
+                self.memory_limits.decrement(entry.byte_len);
 
                 Ok(entry.data)
             }
             Entry::Disk(ref entry) => {
-                let data = DiskUtil::read(&entry.path, Some(entry.byte_len)).await?;
+                let (_, data) = DiskUtil::read(&entry.path, Some(entry.byte_len)).await?;
 
                 // Delete from disk
-                DiskUtil::delete(&entry.path).await?;
+                DiskUtil::delete_or_archive(&entry.path, self.archive_dir.as_deref()).await?;
 
                 // Decrement limits
-                self.disk_limits.current_byte_count -= entry.byte_len;
-                self.disk_limits.current_entry_count -= 1;
+                self.disk_limits.decrement(entry.byte_len);
 
-                Ok(data)
+                let data = self.decompress_disk(entry, Cow::Owned(data)).await?;
+                Ok(data.into_owned())
+            }
+        }
+    }
+
+    async fn verify(&self, entry: &Self::CacheEntry) -> Result<bool> {
+        match entry {
+            Entry::Memory(MemoryEntry {
+                disk_copy: None, ..
+            }) => Ok(true),
+            Entry::Memory(MemoryEntry {
+                disk_copy: Some(entry),
+                ..
+            })
+            | Entry::Disk(entry) => {
+                let file_len = DiskUtil::file_len(&entry.path).await?;
+                Ok(file_len == Some((entry.header_len() + entry.byte_len) as u64))
             }
         }
     }
@@ -218,137 +467,514 @@ impl CacheStrategy for Hybrid {
     async fn delete(&mut self, entry: Self::CacheEntry) -> Result<()> {
         match entry {
             Entry::Memory(entry) => {
+                if let Some(disk_copy) = entry.disk_copy {
+                    self.delete_disk_entry(disk_copy).await?;
+                }
+
                 // Decrement limits
-                self.memory_limits.current_byte_count -= entry.byte_len;
-                self.memory_limits.current_entry_count -= 1;
+                self.memory_limits.decrement(entry.byte_len);
             }
-            Entry::Disk(entry) => {
-                // Delete from disk
-                DiskUtil::delete(&entry.path).await?;
+            Entry::Disk(entry) => self.delete_disk_entry(entry).await?,
+        }
+        Ok(())
+    }
 
-                // Decrement limits
-                self.disk_limits.current_byte_count -= entry.byte_len;
-                self.disk_limits.current_entry_count -= 1;
+    async fn append(&mut self, entry: &mut Self::CacheEntry, data: &[u8]) -> Result<()> {
+        match entry {
+            Entry::Memory(entry) => {
+                if !self.memory_limits.fits_growth(data.len()) {
+                    return Err(crate::Error::LimitExceeded {
+                        limit_kind: Cow::Borrowed(LIMIT_KIND_BYTE_MEMORY),
+                    });
+                }
+
+                // The disk copy would be outdated
+                if let Some(disk_copy) = entry.disk_copy.take() {
+                    self.delete_disk_entry(disk_copy).await?;
+                }
+
+                entry.data.extend_from_slice(data);
+                entry.byte_len += data.len();
+                entry.header.original_len += data.len() as u64;
+                self.memory_limits.grow(data.len());
+            }
+            Entry::Disk(entry) => {
+                // Compressed files can't be appended to
+                if entry.is_disk_compressed() {
+                    return Err(crate::Error::Unsupported {
+                        operation: "append to a compressed entry",
+                    });
+                }
+                if !self.disk_limits.fits_growth(data.len()) {
+                    return Err(crate::Error::LimitExceeded {
+                        limit_kind: Cow::Borrowed(LIMIT_KIND_BYTE_DISK),
+                    });
+                }
+
+                // The header isn't rewritten, since the length of uncompressed
+                // values is taken from the file on recovery
+                DiskUtil::append(&entry.path, data).await?;
+                entry.byte_len += data.len();
+                self.disk_limits.grow(data.len());
             }
         }
         Ok(())
     }
 
+    fn stored_byte_len(&self, entry: &Self::CacheEntry) -> Option<usize> {
+        match entry {
+            Entry::Memory(entry) => Some(entry.byte_len),
+            Entry::Disk(entry) => Some(entry.byte_len),
+        }
+    }
+
+    fn recount(&mut self, entries: Vec<&Self::CacheEntry>) {
+        self.memory_limits.reset();
+        self.disk_limits.reset();
+        for entry in entries {
+            match entry {
+                Entry::Memory(entry) => {
+                    self.memory_limits.increment(entry.byte_len);
+                    // Copies on disk count towards the disk limits
+                    if let Some(disk_copy) = &entry.disk_copy {
+                        self.disk_limits.increment(disk_copy.byte_len);
+                    }
+                }
+                Entry::Disk(entry) => self.disk_limits.increment(entry.byte_len),
+            }
+        }
+    }
+
+    fn entry_location(&self, entry: &Self::CacheEntry) -> EntryLocation {
+        match entry {
+            Entry::Memory(MemoryEntry {
+                disk_copy: None, ..
+            }) => EntryLocation::Memory,
+            Entry::Memory(_) => EntryLocation::MemoryAndDisk,
+            Entry::Disk(_) => EntryLocation::Disk,
+        }
+    }
+
+    fn entry_path<'a>(&self, entry: &'a Self::CacheEntry) -> Option<(&'a Path, u64)> {
+        match entry {
+            // Compressed files can't be used as-is
+            Entry::Memory(MemoryEntry {
+                disk_copy: Some(entry),
+                ..
+            })
+            | Entry::Disk(entry)
+                if !entry.is_disk_compressed() =>
+            {
+                Some((&entry.path, entry.header_len() as u64))
+            }
+            _ => None,
+        }
+    }
+
+    async fn demote<K>(
+        &mut self,
+        key: &K,
+        entry: &Self::CacheEntry,
+    ) -> Result<Option<Self::CacheEntry>>
+    where
+        K: CacheKey + Sync + Send,
+    {
+        let Some(flushed) = FlushableStrategy::flush(self, key, entry).await? else {
+            return Ok(None);
+        };
+        if let Entry::Memory(entry) = entry {
+            self.memory_limits.decrement(entry.byte_len);
+        }
+        Ok(Some(flushed))
+    }
+
+    fn entry_header(&self, entry: &Self::CacheEntry) -> Option<EntryHeader> {
+        match entry {
+            Entry::Memory(entry) => Some(entry.header),
+            Entry::Disk(entry) => entry.header,
+        }
+    }
+
+    fn cache_dir(&self) -> Option<&Path> {
+        Some(&self.cache_dir)
+    }
+
+    fn is_persistent(&self) -> bool {
+        true
+    }
+
     fn get_cache_capacity(&self) -> Option<CacheCapacity> {
         if let (Some(memory_byte_limit), Some(disk_byte_limit)) =
             (self.memory_limits.byte_limit, self.disk_limits.byte_limit)
         {
             Some(CacheCapacity::new(
-                memory_byte_limit + disk_byte_limit,
-                self.memory_limits.current_byte_count + self.disk_limits.current_byte_count,
+                memory_byte_limit.saturating_add(disk_byte_limit),
+                self.memory_limits
+                    .current_byte_count
+                    .saturating_add(self.disk_limits.current_byte_count),
             ))
         } else {
             None
         }
     }
+
+    fn tier_capacity(&self, tier: Tier) -> Option<CacheCapacity> {
+        let limits = match tier {
+            Tier::Memory => &self.memory_limits,
+            Tier::Disk => &self.disk_limits,
+        };
+        limits
+            .byte_limit
+            .map(|byte_limit| CacheCapacity::new(byte_limit, limits.current_byte_count))
+    }
 }
 
 #[async_trait]
 impl RecoverableStrategy for Hybrid {
-    async fn recover<K, F>(&mut self, mut recover_key: F) -> Result<Vec<(K, Self::CacheEntry)>>
+    async fn recover<K, F>(&mut self, recover_key: F) -> Result<Vec<(K, Self::CacheEntry)>>
     where
         K: Send,
         F: Fn(&str) -> Option<K> + Send,
     {
-        // Create the `lost+found` directory
-        let lost_found_dir = self.cache_dir.join("lost+found");
-        std::fs::create_dir_all(&lost_found_dir)?;
-
-        // Closure to move files to the `lost+found` directory
-        let move_to_lost_found = |source: &Path| {
-            // We explcitly ignore any errors here, as we don't want to fail
-            // the entire recovery process because of a single file.
-            let Some(file_name) = source.file_name() else {
-                return;
-            };
-            let target_path = lost_found_dir.join(file_name);
-            _ = std::fs::rename(source, target_path);
-        };
+        self.recover_filtered(recover_key, |_| true).await
+    }
 
-        // Iterate over all files in the cache directory
-        let mut entries = Vec::new();
-        for entry in std::fs::read_dir(&self.cache_dir)?.filter_map(|e| e.ok()) {
-            let path = entry.path();
+    async fn recover_filtered<K, F, P>(
+        &mut self,
+        recover_key: F,
+        should_consider: P,
+    ) -> Result<Vec<(K, Self::CacheEntry)>>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+        P: Fn(&str) -> bool + Send,
+    {
+        let files = DiskUtil::recover_dir(&self.cache_dir, recover_key, should_consider).await?;
 
-            // Skip directories
-            if path.is_dir() {
-                continue;
+        Ok(self.index_files(files))
+    }
+
+    async fn recover_with_report<K, F>(
+        &mut self,
+        recover_key: F,
+    ) -> Result<(Vec<(K, Self::CacheEntry)>, RecoveryReport)>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        let (files, report) =
+            DiskUtil::recover_dir_with_report(&self.cache_dir, recover_key, |_| true).await?;
+
+        Ok((self.index_files(files), report))
+    }
+
+    async fn recover_fast<K, F>(&mut self, recover_key: F) -> Result<Vec<(K, Self::CacheEntry)>>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        let files = DiskUtil::recover_dir_fast(&self.cache_dir, recover_key, |_| true).await?;
+
+        Ok(self.index_files(files))
+    }
+
+    async fn recover_lost_found<K, F>(
+        &mut self,
+        recover_key: F,
+    ) -> Result<Vec<(K, Self::CacheEntry)>>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        let files = DiskUtil::recover_lost_found(&self.cache_dir, recover_key).await?;
+
+        Ok(self.index_files(files))
+    }
+
+    fn forget(&mut self, entry: Self::CacheEntry) {
+        match entry {
+            Entry::Memory(entry) => {
+                if let Some(disk_copy) = entry.disk_copy {
+                    self.disk_limits.decrement(disk_copy.byte_len);
+                }
+                self.memory_limits.decrement(entry.byte_len);
             }
+            Entry::Disk(entry) => self.disk_limits.decrement(entry.byte_len),
+        }
+    }
+}
 
-            // If key recovery fails, we move the entry to the `lost+found` directory.
-            let Some(key) = path
-                .file_name()
-                .and_then(|p| p.to_str())
-                .and_then(&mut recover_key)
-            else {
-                move_to_lost_found(&path);
-                continue;
-            };
+#[async_trait]
+impl FlushableStrategy for Hybrid {
+    async fn flush<K>(
+        &mut self,
+        key: &K,
+        entry: &Self::CacheEntry,
+    ) -> Result<Option<Self::CacheEntry>>
+    where
+        K: CacheKey + Sync + Send,
+    {
+        // We can only flush entries stored in memory
+        let Self::CacheEntry::Memory(entry) = entry else {
+            return Ok(None);
+        };
+
+        // Entries with a copy on disk only need to release their memory
+        if let Some(disk_copy) = &entry.disk_copy {
+            return Ok(Some(Entry::Disk(DiskEntry {
+                path: disk_copy.path.clone(),
+                byte_len: disk_copy.byte_len,
+                header: disk_copy.header,
+            })));
+        }
+
+        let disk_entry = self.write_disk_copy(key, entry).await?;
 
-            // Read file
-            let buf = DiskUtil::read(&path, None).await?;
+        // Return new disk entry
+        Ok(Some(Entry::Disk(disk_entry)))
+    }
+
+    async fn flush_copy<K>(&mut self, key: &K, entry: &mut Self::CacheEntry) -> Result<bool>
+    where
+        K: CacheKey + Sync + Send,
+    {
+        // We can only copy entries that are held in memory only
+        let Self::CacheEntry::Memory(
+            entry @ MemoryEntry {
+                disk_copy: None, ..
+            },
+        ) = entry
+        else {
+            return Ok(false);
+        };
+
+        // The memory entry keeps its data and tracks the copy
+        entry.disk_copy = Some(self.write_disk_copy(key, entry).await?);
+        Ok(true)
+    }
+
+    async fn release_flushed(&mut self, entry: Self::CacheEntry) -> Result<()> {
+        match entry {
+            // Any disk copy is now owned by the flushed entry
+            Entry::Memory(entry) => self.memory_limits.decrement(entry.byte_len),
+            Entry::Disk(entry) => self.delete_disk_entry(entry).await?,
+        }
+        Ok(())
+    }
+}
+
+impl Hybrid {
+    /// Index recovered files as disk entries, counting them towards the disk limits.
+    ///
+    /// Files compressed with a disk compressor are skipped without one, since
+    /// their data can't be read.
+    fn index_files<K>(
+        &mut self,
+        files: Vec<(K, PathBuf, usize, Option<EntryHeader>)>,
+    ) -> Vec<(K, Entry)> {
+        let mut entries = Vec::with_capacity(files.len());
+        for (key, path, byte_len, header) in files {
+            if self.disk_compressor.is_none()
+                && header.map_or(false, |header| header.disk_compressed)
+            {
+                continue;
+            }
 
             // Increment limits
-            self.disk_limits.current_byte_count += buf.len();
-            self.disk_limits.current_entry_count += 1;
+            self.disk_limits.increment(byte_len);
 
             // Push entry
             entries.push((
                 key,
                 Entry::Disk(DiskEntry {
                     path,
-                    byte_len: buf.len(),
+                    byte_len,
+                    header,
                 }),
             ));
         }
+        entries
+    }
 
-        // Return recovered entries
-        Ok(entries)
+    /// Get the header for data written to disk, marking it if the disk compressor
+    /// compressed it.
+    fn disk_header(&self, header: &EntryHeader) -> EntryHeader {
+        EntryHeader {
+            disk_compressed: self.disk_compressor.is_some(),
+            ..*header
+        }
     }
-}
 
-#[async_trait]
-impl FlushableStrategy for Hybrid {
-    async fn flush<K>(
+    /// Decompress data read from a disk entry, if it was compressed with a disk compressor.
+    async fn decompress_disk<'a>(
+        &self,
+        entry: &DiskEntry,
+        data: Cow<'a, [u8]>,
+    ) -> Result<Cow<'a, [u8]>> {
+        if !entry.is_disk_compressed() {
+            return Ok(data);
+        }
+        match &self.disk_compressor {
+            Some(disk_compressor) => disk_compressor.decompress(data).await,
+            None => Err(crate::Error::Unsupported {
+                operation: "read a disk compressed entry without a disk compressor",
+            }),
+        }
+    }
+
+    /// Write a memory entry's data to disk.
+    async fn write_disk_copy<K>(&mut self, key: &K, entry: &MemoryEntry) -> Result<DiskEntry>
+    where
+        K: CacheKey + Sync + Send,
+    {
+        // Compress for disk storage
+        let data = self
+            .disk_compressor
+            .compress(Cow::Borrowed(&entry.data))
+            .await?;
+        let byte_len = data.as_ref().len();
+        let header = self.disk_header(&entry.header);
+
+        // Check if entry fits into disk
+        if let LimitEvaluation::LimitExceeded(reason) = self.disk_limits.evaluate(byte_len) {
+            let limit_kind = Cow::Borrowed(match reason {
+                LimitExceededKind::Bytes => LIMIT_KIND_BYTE_DISK,
+                LimitExceededKind::Entries => LIMIT_KIND_ENTRY_DISK,
+            });
+            return Err(crate::Error::LimitExceeded { limit_kind });
+        }
+
+        // Write to disk
+        let path = DiskUtil::entry_path(&self.cache_dir, self.key_encoding.encode(key))?;
+        DiskUtil::write_atomic(
+            &path,
+            Some(&header),
+            &data,
+            self.file_mode,
+            self.temp_dir.as_deref(),
+        )
+        .await?;
+
+        // Increment limits
+        self.disk_limits.increment(byte_len);
+
+        Ok(DiskEntry {
+            path,
+            byte_len,
+            header: Some(header),
+        })
+    }
+
+    /// Store a value, releasing the `replaced` entry once that succeeded.
+    async fn store<K>(
         &mut self,
         key: &K,
-        entry: &Self::CacheEntry,
-    ) -> Result<Option<Self::CacheEntry>>
+        value: Cow<'_, [u8]>,
+        header: &EntryHeader,
+        replaced: Option<&Entry>,
+    ) -> Result<Entry>
     where
         K: CacheKey + Sync + Send,
     {
-        // We can only flush entries stored in memory
-        let Self::CacheEntry::Memory(entry) = entry else {
-            return Ok(None);
+        let byte_len = value.as_ref().len();
+
+        // The replaced entry's space is free once the new value is stored
+        let (freed_memory, freed_disk) = match replaced {
+            Some(Entry::Memory(entry)) => (
+                Some(entry.byte_len),
+                entry.disk_copy.as_ref().map(|disk_copy| disk_copy.byte_len),
+            ),
+            Some(Entry::Disk(entry)) => (None, Some(entry.byte_len)),
+            None => (None, None),
         };
 
-        // Check if entry fits into disk
-        if let LimitEvaluation::LimitExceeded(reason) = self.disk_limits.evaluate(entry.byte_len) {
-            let limit_kind = Cow::Borrowed(match reason {
-                LimitExceededKind::Bytes => LIMIT_KIND_BYTE_DISK,
-                LimitExceededKind::Entries => LIMIT_KIND_ENTRY_DISK,
-            });
-            return Err(crate::Error::LimitExceeded { limit_kind });
+        // Try to store in memory
+        let entry = if self
+            .memory_limits
+            .evaluate_replacing(byte_len, freed_memory)
+            .is_satisfied()
+        {
+            Entry::Memory(MemoryEntry {
+                data: value.into_owned(),
+                byte_len,
+                header: *header,
+                disk_copy: None,
+            })
+        } else {
+            // Compress for disk storage
+            let value = self.disk_compressor.compress(value).await?;
+            let byte_len = value.as_ref().len();
+            let header = self.disk_header(header);
+
+            // Check if entry fits into disk
+            if let LimitEvaluation::LimitExceeded(reason) =
+                self.disk_limits.evaluate_replacing(byte_len, freed_disk)
+            {
+                let limit_kind = Cow::Borrowed(match reason {
+                    LimitExceededKind::Bytes => LIMIT_KIND_BYTE_DISK,
+                    LimitExceededKind::Entries => LIMIT_KIND_ENTRY_DISK,
+                });
+                return Err(crate::Error::LimitExceeded { limit_kind });
+            }
+
+            // Write to disk
+            let path = DiskUtil::entry_path(&self.cache_dir, self.key_encoding.encode(key))?;
+            DiskUtil::write_atomic(
+                &path,
+                Some(&header),
+                &value,
+                self.file_mode,
+                self.temp_dir.as_deref(),
+            )
+            .await?;
+            Entry::Disk(DiskEntry {
+                path,
+                byte_len,
+                header: Some(header),
+            })
+        };
+
+        // Update limits
+        if let Some(replaced) = replaced {
+            self.release(replaced);
+        }
+        match &entry {
+            Entry::Memory(entry) => self.memory_limits.increment(entry.byte_len),
+            Entry::Disk(entry) => self.disk_limits.increment(entry.byte_len),
+        }
+
+        // The replaced file was overwritten, unless the new entry has another
+        // file or none. Removing it is best effort, as the new entry is stored either way.
+        if let Some(replaced_path) = replaced.and_then(Entry::file_path) {
+            if entry.file_path() != Some(replaced_path) {
+                _ = DiskUtil::delete_or_archive(replaced_path, self.archive_dir.as_deref()).await;
+            }
         }
 
-        // Write to disk
-        let path = self.cache_dir.join(key.to_key());
-        DiskUtil::write(&path, &entry.data).await?;
+        Ok(entry)
+    }
 
-        // Increment limits
-        self.disk_limits.current_byte_count += entry.byte_len;
-        self.disk_limits.current_entry_count += 1;
+    /// Decrement the limit counters for a removed entry, including its disk copy.
+    fn release(&mut self, entry: &Entry) {
+        match entry {
+            Entry::Memory(entry) => {
+                self.memory_limits.decrement(entry.byte_len);
+                if let Some(disk_copy) = &entry.disk_copy {
+                    self.disk_limits.decrement(disk_copy.byte_len);
+                }
+            }
+            Entry::Disk(entry) => self.disk_limits.decrement(entry.byte_len),
+        }
+    }
 
-        // Return new disk entry
-        Ok(Some(Entry::Disk(DiskEntry {
-            path,
-            byte_len: entry.byte_len,
-        })))
+    /// Delete an entry's file from disk.
+    async fn delete_disk_entry(&mut self, entry: DiskEntry) -> Result<()> {
+        // Delete from disk
+        DiskUtil::delete_or_archive(&entry.path, self.archive_dir.as_deref()).await?;
+
+        // Decrement limits
+        self.disk_limits.decrement(entry.byte_len);
+        Ok(())
     }
 }
 
@@ -357,7 +983,10 @@ mod tests {
     use std::fs::metadata;
 
     use super::{Hybrid, Limits, LIMIT_KIND_BYTE_DISK, LIMIT_KIND_ENTRY_DISK};
-    use crate::{async_test, utils::test::TempDir, Cache, Error, NO_COMPRESSION};
+    use crate::{
+        async_test, utils::test::TempDir, Cache, CacheBuilder, EntryLocation, Error, FlushMode,
+        FlushOrder, Tier, NO_COMPRESSION,
+    };
 
     async_test! {
         async fn test_default_strategy() {
@@ -459,16 +1088,15 @@ mod tests {
             assert!(metadata(temp_dir.as_ref().join("baz")).unwrap().is_file());
             assert!(metadata(temp_dir.as_ref().join("bax")).unwrap().is_file());
 
-            match cache.put("quix", b"quix".to_vec()).await {
-                Err(err) => match err {
+            if let Err(err) = cache.put("quix", b"quix".to_vec()).await {
+                match err {
                     Error::LimitExceeded { limit_kind } => {
                         assert_eq!(limit_kind, LIMIT_KIND_BYTE_DISK);
                     }
                     _ => {
                         panic!("Unexpected error: {:?}", err);
                     }
-                },
-                _ => (),
+                }
             }
         }
 
@@ -493,16 +1121,15 @@ mod tests {
             assert!(metadata(temp_dir.as_ref().join("baz")).unwrap().is_file());
             assert!(metadata(temp_dir.as_ref().join("bax")).unwrap().is_file());
 
-            match cache.put("quix", b"quix".to_vec()).await {
-                Err(err) => match err {
+            if let Err(err) = cache.put("quix", b"quix".to_vec()).await {
+                match err {
                     Error::LimitExceeded { limit_kind } => {
                         assert_eq!(limit_kind, LIMIT_KIND_ENTRY_DISK);
                     }
                     _ => {
                         panic!("Unexpected error: {:?}", err);
                     }
-                },
-                _ => (),
+                }
             }
         }
 
@@ -561,5 +1188,462 @@ mod tests {
             assert_eq!(cache.strategy().disk_limits.current_byte_count, 6);
             assert_eq!(cache.strategy().disk_limits.current_entry_count, 2);
         }
+
+        async fn test_strategy_with_memory_byte_and_entry_limit() {
+            let temp_dir = TempDir::new();
+
+            let mut cache = Cache::new(Hybrid::new(
+                temp_dir.as_ref(),
+                Limits::new(Some(100), Some(2)),
+                Limits::default(),
+            ), NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+            cache.put("baz", b"baz".to_vec()).await.unwrap();
+
+            assert_eq!(cache.strategy().memory_limits.current_entry_count, 2);
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 1);
+            assert!(metadata(temp_dir.as_ref().join("baz")).unwrap().is_file());
+        }
+
+        async fn test_flush_key() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Hybrid::new(
+                temp_dir.as_ref(),
+                Limits::default(),
+                Limits::default(),
+            ), NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".as_slice()).await.unwrap();
+            cache.put("bar", b"bar".as_slice()).await.unwrap();
+
+            assert!(cache.flush_key("foo").await.unwrap());
+            assert!(!cache.flush_key("foo").await.unwrap());
+            assert!(!cache.flush_key("baz").await.unwrap());
+
+            assert_eq!(cache.strategy().memory_limits.current_byte_count, 3);
+            assert_eq!(cache.strategy().memory_limits.current_entry_count, 1);
+            assert_eq!(cache.strategy().disk_limits.current_byte_count, 3);
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 1);
+            assert!(metadata(temp_dir.as_ref().join("foo")).unwrap().is_file());
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+        }
+
+        async fn test_put_located() {
+            let temp_dir = TempDir::new();
+            let strategy = Hybrid::default()
+                .with_cache_dir(temp_dir.as_ref())
+                .with_memory_entry_limit(1);
+            let mut cache = Cache::new(strategy, NO_COMPRESSION).await.unwrap();
+
+            let location = cache.put_located("foo", b"foo".as_slice()).await.unwrap();
+            assert_eq!(location, EntryLocation::Memory);
+
+            let location = cache.put_located("bar", b"bar".as_slice()).await.unwrap();
+            assert_eq!(location, EntryLocation::Disk);
+
+            assert!(!cache.is_durable("foo"));
+            assert!(cache.is_durable("bar"));
+            assert!(!cache.is_durable("baz"));
+        }
+
+        async fn test_tier_capacity() {
+            let temp_dir = TempDir::new();
+            let strategy = Hybrid::default()
+                .with_cache_dir(temp_dir.as_ref())
+                .with_memory_byte_limit(4)
+                .with_disk_byte_limit(1024);
+            let mut cache = Cache::new(strategy, NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".as_slice()).await.unwrap();
+            cache.put("bar", b"bar".as_slice()).await.unwrap();
+
+            let memory = cache.tier_capacity(Tier::Memory).unwrap();
+            assert_eq!(memory.total(), 4);
+            assert_eq!(memory.used(), 3);
+
+            let disk = cache.tier_capacity(Tier::Disk).unwrap();
+            assert_eq!(disk.total(), 1024);
+            assert_eq!(disk.used(), 3);
+        }
+
+        async fn test_total_stored_bytes() {
+            let temp_dir = TempDir::new();
+            let strategy = Hybrid::default()
+                .with_cache_dir(temp_dir.as_ref())
+                .with_memory_byte_limit(4)
+                .with_disk_byte_limit(1024);
+            let mut cache = Cache::new(strategy, NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".as_slice()).await.unwrap();
+            cache.put("bar", b"barbar".as_slice()).await.unwrap();
+
+            assert_eq!(cache.total_stored_bytes(), 9);
+            assert_eq!(cache.total_stored_bytes(), cache.capacity().unwrap().used());
+        }
+
+        async fn test_recover_already_indexed() {
+            let temp_dir = TempDir::new();
+
+            // populate disk
+            {
+                let mut cache = Cache::new(Hybrid::new(
+                    temp_dir.as_ref(),
+                    Limits::new(None, Some(0)),
+                    Limits::default(),
+                ), NO_COMPRESSION).await.unwrap();
+                cache.put("foo", b"old".to_vec()).await.unwrap();
+                cache.put("bar", b"bar".to_vec()).await.unwrap();
+            }
+
+            // put before recovering, landing in memory
+            let mut cache = Cache::new(Hybrid::new(
+                temp_dir.as_ref(),
+                Limits::new(Some(1024), None),
+                Limits::new(Some(1024), None),
+            ), NO_COMPRESSION).await.unwrap();
+            cache.put("foo".to_string(), b"new".to_vec()).await.unwrap();
+
+            let recovered_items = cache.recover(|k| Some(k.to_string())).await.unwrap();
+            assert_eq!(recovered_items, 1);
+            assert_eq!(cache.get("foo".to_string()).await.unwrap(), b"new".as_slice());
+
+            // The stale disk copy is deleted
+            assert!(!temp_dir.as_ref().join("foo").exists());
+            assert_eq!(cache.tier_capacity(Tier::Disk).unwrap().used(), 3);
+        }
+
+        async fn test_flush_to_watermark() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Hybrid::new(
+                temp_dir.as_ref(),
+                Limits::new(Some(10), None),
+                Limits::default(),
+            ), NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".as_slice()).await.unwrap();
+            assert_eq!(cache.flush_to_watermark(0.5).await.unwrap(), 0);
+
+            cache.put("bar", b"bar".as_slice()).await.unwrap();
+            cache.put("baz", b"baz".as_slice()).await.unwrap();
+            assert_eq!(cache.flush_to_watermark(0.5).await.unwrap(), 2);
+
+            assert_eq!(cache.strategy().memory_limits.current_byte_count, 3);
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 2);
+        }
+
+        async fn test_flush_high_watermark() {
+            let temp_dir = TempDir::new();
+            let mut cache = CacheBuilder
+                .with_strategy(Hybrid::new(temp_dir.as_ref(), Limits::new(Some(10), None), Limits::default()))
+                .with_flush_order(FlushOrder::OldestFirst)
+                .flush_high_watermark(0.5)
+                .build()
+                .await
+                .unwrap();
+
+            cache.put("foo", b"foo".as_slice()).await.unwrap();
+            cache.put("bar", b"bar".as_slice()).await.unwrap();
+            assert_eq!(cache.strategy().memory_limits.current_byte_count, 3);
+            cache.put("baz", b"baz".as_slice()).await.unwrap();
+
+            // Older entries are flushed, the one just put stays in memory
+            assert_eq!(cache.strategy().memory_limits.current_byte_count, 3);
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 2);
+            assert!(cache.is_durable("foo"));
+            assert!(cache.is_durable("bar"));
+            assert!(!cache.is_durable("baz"));
+            for key in ["foo", "bar", "baz"] {
+                assert_eq!(cache.get(key).await.unwrap(), key.as_bytes());
+            }
+
+            // Overwriting an entry keeps it in memory
+            cache.put("baz", b"qux".as_slice()).await.unwrap();
+            assert_eq!(cache.get("baz").await.unwrap(), b"qux".as_slice());
+            assert_eq!(cache.strategy().memory_limits.current_entry_count, 1);
+
+            for high_watermark in [f64::NAN, -0.1, 1.5] {
+                let result = CacheBuilder
+                    .with_strategy(Hybrid::default())
+                    .flush_high_watermark(high_watermark)
+                    .build::<&str>()
+                    .await;
+                assert!(matches!(result, Err(Error::InvalidOption { .. })));
+            }
+        }
+
+        async fn test_tier_stats() {
+            let temp_dir = TempDir::new();
+            let strategy = Hybrid::default()
+                .with_cache_dir(temp_dir.as_ref())
+                .with_memory_entry_limit(1);
+            let mut cache = Cache::new(strategy, NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".as_slice()).await.unwrap();
+            cache.put("bar", b"barbar".as_slice()).await.unwrap();
+            cache.put("baz", b"baz".as_slice()).await.unwrap();
+
+            let stats = cache.tier_stats();
+            assert_eq!(stats.memory_keys(), 1);
+            assert_eq!(stats.memory_bytes(), 3);
+            assert_eq!(stats.disk_keys(), 2);
+            assert_eq!(stats.disk_bytes(), 9);
+        }
+
+        async fn test_entry_path() {
+            let temp_dir = TempDir::new();
+            let strategy = Hybrid::default()
+                .with_cache_dir(temp_dir.as_ref())
+                .with_memory_entry_limit(1);
+            let mut cache = Cache::new(strategy, NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".as_slice()).await.unwrap();
+            cache.put("bar", b"bar".as_slice()).await.unwrap();
+
+            assert_eq!(cache.entry_path("foo"), None);
+            let (path, offset) = cache.entry_path("bar").unwrap();
+            assert_eq!(path, temp_dir.as_ref().join("bar"));
+            let data = std::fs::read(path).unwrap();
+            assert_eq!(&data[offset as usize..], b"bar");
+            assert_eq!(cache.entry_path("baz"), None);
+        }
+
+        async fn test_flush_copy() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Hybrid::new(
+                temp_dir.as_ref(),
+                Limits::default(),
+                Limits::default(),
+            ), NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".as_slice()).await.unwrap();
+            cache.put("bar", b"bar".as_slice()).await.unwrap();
+
+            assert_eq!(cache.flush_with_mode(FlushMode::Copy).await.unwrap(), 2);
+            assert_eq!(cache.flush_with_mode(FlushMode::Copy).await.unwrap(), 0);
+
+            // Entries stay in memory and are written to disk
+            assert_eq!(cache.strategy().memory_limits.current_byte_count, 6);
+            assert_eq!(cache.strategy().memory_limits.current_entry_count, 2);
+            assert_eq!(cache.strategy().disk_limits.current_byte_count, 6);
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 2);
+            assert!(metadata(temp_dir.as_ref().join("foo")).unwrap().is_file());
+            assert_eq!(cache.get_detailed("foo").await.unwrap().1.location(), EntryLocation::MemoryAndDisk);
+            assert!(cache.is_durable("foo"));
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+
+            // Moving releases memory and keeps the copy
+            assert_eq!(cache.flush().await.unwrap(), 2);
+            assert_eq!(cache.strategy().memory_limits.current_byte_count, 0);
+            assert_eq!(cache.strategy().memory_limits.current_entry_count, 0);
+            assert_eq!(cache.strategy().disk_limits.current_byte_count, 6);
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 2);
+            assert_eq!(cache.get_detailed("foo").await.unwrap().1.location(), EntryLocation::Disk);
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+        }
+
+        async fn test_flush_copy_delete() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Hybrid::new(
+                temp_dir.as_ref(),
+                Limits::default(),
+                Limits::default(),
+            ), NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo".to_string(), b"foo".as_slice()).await.unwrap();
+            cache.put("bar".to_string(), b"bar".as_slice()).await.unwrap();
+            cache.flush_with_mode(FlushMode::Copy).await.unwrap();
+
+            // Recovering doesn't duplicate or delete copied entries
+            assert_eq!(cache.recover(|key| Some(key.to_string())).await.unwrap(), 0);
+            assert!(metadata(temp_dir.as_ref().join("foo")).unwrap().is_file());
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 2);
+
+            cache.delete("foo".to_string()).await.unwrap();
+            assert!(metadata(temp_dir.as_ref().join("foo")).is_err());
+            assert_eq!(cache.take("bar".to_string()).await.unwrap(), b"bar".as_slice());
+            assert!(metadata(temp_dir.as_ref().join("bar")).is_err());
+
+            assert_eq!(cache.strategy().memory_limits.current_byte_count, 0);
+            assert_eq!(cache.strategy().memory_limits.current_entry_count, 0);
+            assert_eq!(cache.strategy().disk_limits.current_byte_count, 0);
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 0);
+        }
+
+        async fn test_append() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(Hybrid::new(
+                temp_dir.as_ref(),
+                Limits::new(Some(6), None),
+                Limits::default(),
+            ), NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".as_slice()).await.unwrap();
+            cache.flush_with_mode(FlushMode::Copy).await.unwrap();
+
+            // Appending drops the outdated disk copy
+            cache.append("foo", b"bar").await.unwrap();
+            assert_eq!(cache.get("foo").await.unwrap(), b"foobar".as_slice());
+            assert!(!cache.is_durable("foo"));
+            assert!(metadata(temp_dir.as_ref().join("foo")).is_err());
+            assert_eq!(cache.strategy().memory_limits.current_byte_count, 6);
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 0);
+
+            let result = cache.append("foo", b"baz").await;
+            assert!(matches!(result, Err(Error::LimitExceeded { .. })));
+
+            // Entries on disk are appended to in place
+            cache.flush().await.unwrap();
+            cache.append("foo", b"baz").await.unwrap();
+            assert_eq!(cache.get("foo").await.unwrap(), b"foobarbaz".as_slice());
+            assert_eq!(cache.strategy().disk_limits.current_byte_count, 9);
+        }
+
+        async fn test_flush_oldest_first() {
+            let temp_dir = TempDir::new();
+            let mut cache = CacheBuilder
+                .with_strategy(Hybrid::new(
+                    temp_dir.as_ref(),
+                    Limits::default(),
+                    Limits::new(None, Some(2)),
+                ))
+                .with_flush_order(FlushOrder::OldestFirst)
+                .build()
+                .await
+                .unwrap();
+
+            for key in ["foo", "bar", "baz"] {
+                cache.put(key, key.as_bytes()).await.unwrap();
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+
+            // The newest entry stays in memory once the disk tier is full
+            let result = cache.flush().await;
+            assert!(matches!(result, Err(Error::LimitExceeded { .. })));
+            assert!(cache.is_durable("foo"));
+            assert!(cache.is_durable("bar"));
+            assert!(!cache.is_durable("baz"));
+            assert_eq!(cache.strategy().memory_limits.current_entry_count, 1);
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 2);
+        }
+
+        async fn test_recovery_fast() {
+            let temp_dir = TempDir::new();
+
+            // populate cache
+            {
+                let mut cache = Cache::new(Hybrid::new(
+                    temp_dir.as_ref(),
+                    Limits::new(None, Some(1)),
+                    Limits::default(),
+                ), NO_COMPRESSION).await.unwrap();
+
+                cache.put("foo", b"foo".to_vec()).await.unwrap();
+                cache.put("bar", b"bar".to_vec()).await.unwrap();
+                cache.put("baz", b"baz".to_vec()).await.unwrap();
+            }
+
+            // recover cache
+            let mut cache = Cache::new(Hybrid::new(
+                temp_dir.as_ref(),
+                Limits::default(),
+                Limits::default(),
+            ), NO_COMPRESSION).await.unwrap();
+            assert_eq!(cache.recover_fast(|k| Some(k.to_string())).await.unwrap(), 2);
+            assert_eq!(cache.strategy().disk_limits.current_byte_count, 6);
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 2);
+        }
+
+        async fn test_overwrite() {
+            let temp_dir = TempDir::new();
+
+            let mut cache = Cache::new(Hybrid::new(
+                temp_dir.as_ref(),
+                Limits::new(Some(3), None),
+                Limits::new(Some(6), None),
+            ), NO_COMPRESSION).await.unwrap();
+
+            // Overwrites move entries between memory and disk
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("foo", b"foobar".to_vec()).await.unwrap();
+            assert_eq!(cache.strategy().memory_limits.current_byte_count, 0);
+            assert_eq!(cache.strategy().disk_limits.current_byte_count, 6);
+
+            // A failed overwrite keeps the old value
+            let result = cache.put("foo", b"foobarbaz".to_vec()).await;
+            assert!(matches!(result, Err(Error::LimitExceeded { .. })));
+            assert_eq!(cache.get("foo").await.unwrap(), b"foobar".as_slice());
+
+            // The file of an entry moved to memory is removed
+            cache.put("foo", b"bar".to_vec()).await.unwrap();
+            assert!(!temp_dir.as_ref().join("foo").exists());
+            assert_eq!(cache.strategy().memory_limits.current_byte_count, 3);
+            assert_eq!(cache.strategy().memory_limits.current_entry_count, 1);
+            assert_eq!(cache.strategy().disk_limits.current_byte_count, 0);
+            assert_eq!(cache.strategy().disk_limits.current_entry_count, 0);
+        }
+    }
+    #[cfg(feature = "comp_zstd")]
+    async_test! {
+        async fn test_disk_compression() {
+            use crate::{compression::Zstd, utils::test::create_arb_data};
+
+            let temp_dir = TempDir::new();
+            let data = create_arb_data(1024);
+            let mut cache = Cache::new(
+                Hybrid::new(temp_dir.as_ref(), Limits::new(None, Some(1)), Limits::default())
+                    .with_disk_compression(Zstd::default()),
+                NO_COMPRESSION,
+            ).await.unwrap();
+
+            cache.put("foo", data.clone()).await.unwrap();
+            cache.put("bar", data.clone()).await.unwrap();
+
+            // Memory entries are stored as-is, disk entries are compressed
+            assert_eq!(cache.strategy().memory_limits.current_byte_count, 1024);
+            assert!(cache.strategy().disk_limits.current_byte_count < 1024);
+            assert!(metadata(temp_dir.as_ref().join("bar")).unwrap().len() < 1024);
+
+            cache.flush().await.unwrap();
+            assert!(metadata(temp_dir.as_ref().join("foo")).unwrap().len() < 1024);
+
+            assert_eq!(cache.get("foo").await.unwrap(), data.as_slice());
+            assert_eq!(cache.take("bar").await.unwrap(), data);
+        }
+
+        async fn test_recover_disk_compression() {
+            use crate::{compression::Zstd, utils::test::create_arb_data};
+
+            let temp_dir = TempDir::new();
+            let data = create_arb_data(1024);
+            let hybrid = || Hybrid::new(temp_dir.as_ref(), Limits::new(None, Some(0)), Limits::default());
+            {
+                let mut cache = Cache::new(hybrid().with_disk_compression(Zstd::default()), NO_COMPRESSION).await.unwrap();
+                cache.put("foo".to_string(), data.clone()).await.unwrap();
+                let mut cache = Cache::new(hybrid(), NO_COMPRESSION).await.unwrap();
+                cache.put("bar".to_string(), data.clone()).await.unwrap();
+            }
+
+            // Compressed files are skipped without a disk compressor
+            let mut cache = Cache::new(hybrid(), NO_COMPRESSION).await.unwrap();
+            assert_eq!(cache.recover(|key| Some(key.to_string())).await.unwrap(), 1);
+            assert!(!cache.exists("foo".to_string()));
+            assert!(temp_dir.as_ref().join("foo").is_file());
+            assert_eq!(cache.get("bar".to_string()).await.unwrap(), data.as_slice());
+
+            // Uncompressed files are read as-is with one
+            let mut cache = Cache::new(hybrid().with_disk_compression(Zstd::default()), NO_COMPRESSION).await.unwrap();
+            assert_eq!(cache.recover(|key| Some(key.to_string())).await.unwrap(), 2);
+            assert_eq!(cache.get("foo".to_string()).await.unwrap(), data.as_slice());
+            assert_eq!(cache.get("bar".to_string()).await.unwrap(), data.as_slice());
+            drop(cache);
+
+            // Disk can't decompress them either
+            let disk = crate::strategies::Disk::new(temp_dir.as_ref(), None, None);
+            let mut cache = Cache::new(disk, NO_COMPRESSION).await.unwrap();
+            assert_eq!(cache.recover(|key| Some(key.to_string())).await.unwrap(), 1);
+            assert!(!cache.exists("foo".to_string()));
+        }
     }
 }
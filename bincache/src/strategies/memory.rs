@@ -1,21 +1,63 @@
 use async_trait::async_trait;
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use crate::{CacheCapacity, CacheKey, CacheStrategy, Result};
+use crate::{CacheCapacity, CacheKey, CacheStrategy, DiskUtil, EntryLocation, Result, Tier};
 
 const LIMIT_KIND_BYTE: &str = "Stored bytes";
 const LIMIT_KIND_ENTRY: &str = "Stored entries";
+const LIMIT_KIND_SPILL_BYTE: &str = "Spilled bytes";
 
 #[derive(Debug)]
 pub struct Entry {
-    data: Vec<u8>,
+    data: Data,
     byte_len: usize,
 }
 
+/// Where the data of an [Entry] is held.
+#[derive(Debug)]
+enum Data {
+    /// In memory.
+    Memory(Arc<[u8]>),
+    /// In a file, because the value alone exceeds the byte limit.
+    Spilled(SpillFile),
+}
+
+/// The file of a spilled value, removed when its entry is dropped.
+#[derive(Debug)]
+struct SpillFile(PathBuf);
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(&self.0);
+    }
+}
+
 /// Memory-based cache strategy.
 ///
 /// This strategy stores entries in memory. It can be configured to limit the
 /// number of bytes and/or entries that can be stored.
+///
+/// Entries are stored as `Arc<[u8]>`, so [Cache::get_shared](crate::Cache::get_shared)
+/// can hand out uncompressed entries without copying them.
+///
+/// ## Compression
+/// With a compressor configured on the [Cache](crate::Cache), entries are kept
+/// in memory compressed and decompressed on every read. This trades CPU for
+/// holding more entries within the same amount of memory.
+///
+/// The byte limit applies to the stored, i.e. compressed, size of entries.
+/// Use [`enforce_uncompressed_byte_limit`](crate::cache_builder::CacheBuilderWithStrategy::enforce_uncompressed_byte_limit)
+/// to bound the uncompressed size instead.
+///
+/// ## Oversized values
+/// A value larger than the byte limit can't be stored, even in an empty cache.
+/// With a [spill directory](Self::with_spill_dir), such values are written to a
+/// file instead. See [Hybrid](crate::strategies::Hybrid) to move entries between
+/// memory and disk based on limits.
 #[derive(Default, Debug)]
 pub struct Memory {
     /// The maximum number of bytes that can be stored.
@@ -26,6 +68,12 @@ pub struct Memory {
     current_byte_count: usize,
     /// The current number of entries stored.
     current_entry_count: usize,
+    /// The directory values exceeding the byte limit are written to.
+    spill_dir: Option<PathBuf>,
+    /// The maximum number of bytes that can be spilled.
+    spill_byte_limit: Option<usize>,
+    /// The current number of bytes spilled.
+    current_spill_byte_count: usize,
 }
 
 impl Memory {
@@ -37,74 +85,324 @@ impl Memory {
             ..Default::default()
         }
     }
-}
 
-#[async_trait]
-impl CacheStrategy for Memory {
-    type CacheEntry = Entry;
+    /// Write values that exceed the byte limit on their own to files in `spill_dir`,
+    /// instead of rejecting them.
+    ///
+    /// Spilled entries count towards the entry limit, but not the byte limit.
+    /// They are read from disk on every access and can't be shared without copying.
+    /// Spilled files aren't recovered: they are removed along with their entries,
+    /// e.g. when the cache is dropped. Files left behind by other processes, e.g.
+    /// after a crash, are removed when the cache is set up, so `spill_dir` must not
+    /// be shared between processes.
+    pub fn with_spill_dir<'a>(mut self, spill_dir: impl Into<Cow<'a, Path>>) -> Self {
+        self.spill_dir = Some(spill_dir.into().into_owned());
+        self
+    }
 
-    async fn put<'a, K, V>(&mut self, _key: &K, value: V) -> Result<Self::CacheEntry>
-    where
-        K: CacheKey + Sync + Send,
-        V: Into<Cow<'a, [u8]>> + Send,
-    {
-        let value = value.into();
+    /// Limit the number of bytes that can be [spilled](Self::with_spill_dir) to disk.
+    ///
+    /// Values that would exceed it are rejected, like without a spill directory.
+    pub fn with_spill_byte_limit(mut self, spill_byte_limit: usize) -> Self {
+        self.spill_byte_limit = Some(spill_byte_limit);
+        self
+    }
+
+    /// Get the directory to spill a value of `byte_len` bytes to, if it has to be spilled.
+    fn spill_dir_for(&self, byte_len: usize) -> Option<&Path> {
+        let byte_limit = self.byte_limit?;
+        if byte_len <= byte_limit {
+            return None;
+        }
+        self.spill_dir.as_deref()
+    }
+
+    /// Store a value, releasing the `replaced` entry once that succeeded.
+    async fn store(&mut self, value: Cow<'_, [u8]>, replaced: Option<&Entry>) -> Result<Entry> {
         let byte_len = value.as_ref().len();
+        let spill_dir = self.spill_dir_for(byte_len);
+
+        // The replaced entry's space is free once the new value is stored
+        let (freed_byte_count, freed_spill_byte_count, freed_entry_count) = match replaced {
+            Some(Entry {
+                data: Data::Memory(_),
+                byte_len,
+            }) => (*byte_len, 0, 1),
+            // Spilled entries don't count towards the byte limit
+            Some(Entry { byte_len, .. }) => (0, *byte_len, 1),
+            None => (0, 0, 0),
+        };
 
         // Check if the byte limit has been reached.
         if let Some(byte_limit) = self.byte_limit {
-            if self.current_byte_count + byte_len > byte_limit {
+            let byte_count = self.current_byte_count.saturating_sub(freed_byte_count);
+            if spill_dir.is_none() && byte_count.saturating_add(byte_len) > byte_limit {
                 return Err(crate::Error::LimitExceeded {
                     limit_kind: LIMIT_KIND_BYTE.into(),
                 });
             }
         }
 
+        // Check if the spill byte limit has been reached.
+        if let (Some(_), Some(spill_byte_limit)) = (spill_dir, self.spill_byte_limit) {
+            let spill_byte_count = self
+                .current_spill_byte_count
+                .saturating_sub(freed_spill_byte_count);
+            if spill_byte_count.saturating_add(byte_len) > spill_byte_limit {
+                return Err(crate::Error::LimitExceeded {
+                    limit_kind: LIMIT_KIND_SPILL_BYTE.into(),
+                });
+            }
+        }
+
         // Check if entry limit has been reached.
         if let Some(entry_limit) = self.entry_limit {
-            if self.current_entry_count + 1 > entry_limit {
+            let entry_count = self.current_entry_count.saturating_sub(freed_entry_count);
+            if entry_count.saturating_add(1) > entry_limit {
                 return Err(crate::Error::LimitExceeded {
                     limit_kind: LIMIT_KIND_ENTRY.into(),
                 });
             }
         }
 
-        // Increment limits
-        self.current_byte_count += byte_len;
-        self.current_entry_count += 1;
+        // Spill values exceeding the byte limit to disk. Each spill gets a new file,
+        // so a replaced value stays intact until the new one is written.
+        let entry = match spill_dir {
+            Some(spill_dir) => {
+                let path = spill_dir.join(DiskUtil::unique_file_name("spill"));
+                DiskUtil::write_atomic(&path, None, value.as_ref(), None, None).await?;
+                Entry {
+                    data: Data::Spilled(SpillFile(path)),
+                    byte_len,
+                }
+            }
+            None => Entry {
+                data: Data::Memory(Arc::from(value)),
+                byte_len,
+            },
+        };
+
+        // Update limits
+        if let Some(replaced) = replaced {
+            self.release(replaced);
+        }
+        match entry.data {
+            Data::Memory(_) => self.increment_limits(byte_len),
+            Data::Spilled(_) => {
+                self.increment_limits(0);
+                self.current_spill_byte_count =
+                    self.current_spill_byte_count.saturating_add(byte_len);
+            }
+        }
+
+        Ok(entry)
+    }
+
+    /// Increment the limit counters for a newly stored entry.
+    fn increment_limits(&mut self, byte_len: usize) {
+        self.current_byte_count = self.current_byte_count.saturating_add(byte_len);
+        self.current_entry_count = self.current_entry_count.saturating_add(1);
+    }
+
+    /// Decrement the limit counters for a removed entry.
+    fn decrement_limits(&mut self, byte_len: usize) {
+        debug_assert!(self.current_byte_count >= byte_len, "byte count underflow");
+        debug_assert!(self.current_entry_count >= 1, "entry count underflow");
+        self.current_byte_count = self.current_byte_count.saturating_sub(byte_len);
+        self.current_entry_count = self.current_entry_count.saturating_sub(1);
+    }
+
+    /// Decrement the limit counters for a removed entry, depending on where it's held.
+    fn release(&mut self, entry: &Entry) {
+        match entry.data {
+            Data::Memory(_) => self.decrement_limits(entry.byte_len),
+            // Spilled entries don't count towards the byte limit
+            Data::Spilled(_) => {
+                self.decrement_limits(0);
+                self.current_spill_byte_count =
+                    self.current_spill_byte_count.saturating_sub(entry.byte_len);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl CacheStrategy for Memory {
+    type CacheEntry = Entry;
+
+    async fn setup(&mut self) -> Result<()> {
+        if let Some(spill_dir) = &self.spill_dir {
+            DiskUtil::setup_dir(spill_dir, None).await?;
+            DiskUtil::remove_leftover_spill_files(spill_dir)?;
+        }
+        Ok(())
+    }
+
+    async fn put<'a, K, V>(&mut self, key: &K, value: V) -> Result<Self::CacheEntry>
+    where
+        K: CacheKey + Sync + Send,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        _ = key;
+        self.store(value.into(), None).await
+    }
 
-        Ok(Entry {
-            data: value.into_owned(),
-            byte_len,
-        })
+    async fn replace<'a, K, V>(
+        &mut self,
+        key: &K,
+        old: &Self::CacheEntry,
+        value: V,
+    ) -> Result<Self::CacheEntry>
+    where
+        K: CacheKey + Sync + Send,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        _ = key;
+        self.store(value.into(), Some(old)).await
     }
 
     async fn get<'a>(&self, entry: &'a Self::CacheEntry) -> Result<Cow<'a, [u8]>> {
-        Ok(entry.data.as_slice().into())
+        match &entry.data {
+            Data::Memory(data) => Ok(Cow::Borrowed(data)),
+            Data::Spilled(SpillFile(path)) => DiskUtil::read(path, Some(entry.byte_len))
+                .await
+                .map(|(_, data)| Cow::Owned(data)),
+        }
     }
 
     async fn take(&mut self, entry: Self::CacheEntry) -> Result<Vec<u8>> {
-        // Decrement limits
-        self.current_byte_count -= entry.byte_len;
-        self.current_entry_count -= 1;
+        let data = match &entry.data {
+            Data::Memory(data) => data.to_vec(),
+            Data::Spilled(SpillFile(path)) => DiskUtil::read(path, Some(entry.byte_len)).await?.1,
+        };
+        self.delete(entry).await?;
 
-        Ok(entry.data)
+        Ok(data)
     }
 
     async fn delete(&mut self, entry: Self::CacheEntry) -> Result<()> {
-        Ok(_ = self.take(entry).await?)
+        if let Data::Spilled(SpillFile(path)) = &entry.data {
+            DiskUtil::delete_or_archive(path, None).await?;
+        }
+
+        // Decrement limits
+        self.release(&entry);
+
+        Ok(())
+    }
+
+    async fn verify(&self, entry: &Self::CacheEntry) -> Result<bool> {
+        match &entry.data {
+            Data::Memory(_) => Ok(true),
+            Data::Spilled(SpillFile(path)) => {
+                let file_len = DiskUtil::file_len(path).await?;
+                Ok(file_len == Some(entry.byte_len as u64))
+            }
+        }
+    }
+
+    async fn append(&mut self, entry: &mut Self::CacheEntry, data: &[u8]) -> Result<()> {
+        let value = match &entry.data {
+            Data::Memory(value) => value,
+            Data::Spilled(SpillFile(path)) => {
+                // Check if the spill byte limit has been reached.
+                if let Some(spill_byte_limit) = self.spill_byte_limit {
+                    if self.current_spill_byte_count.saturating_add(data.len()) > spill_byte_limit {
+                        return Err(crate::Error::LimitExceeded {
+                            limit_kind: LIMIT_KIND_SPILL_BYTE.into(),
+                        });
+                    }
+                }
+
+                DiskUtil::append(path, data).await?;
+                entry.byte_len += data.len();
+                self.current_spill_byte_count =
+                    self.current_spill_byte_count.saturating_add(data.len());
+                return Ok(());
+            }
+        };
+
+        // Check if the byte limit has been reached.
+        if let Some(byte_limit) = self.byte_limit {
+            if self.current_byte_count.saturating_add(data.len()) > byte_limit {
+                return Err(crate::Error::LimitExceeded {
+                    limit_kind: LIMIT_KIND_BYTE.into(),
+                });
+            }
+        }
+
+        // Shared data can't grow in place
+        let mut new_value = Vec::with_capacity(value.len() + data.len());
+        new_value.extend_from_slice(value);
+        new_value.extend_from_slice(data);
+        entry.data = Data::Memory(Arc::from(new_value));
+        entry.byte_len += data.len();
+
+        // Increment limits
+        self.current_byte_count = self.current_byte_count.saturating_add(data.len());
+
+        Ok(())
+    }
+
+    fn get_shared(&self, entry: &Self::CacheEntry) -> Option<Arc<[u8]>> {
+        match &entry.data {
+            Data::Memory(data) => Some(Arc::clone(data)),
+            Data::Spilled(_) => None,
+        }
+    }
+
+    fn stored_byte_len(&self, entry: &Self::CacheEntry) -> Option<usize> {
+        Some(entry.byte_len)
+    }
+
+    fn recount(&mut self, entries: Vec<&Self::CacheEntry>) {
+        self.current_byte_count = 0;
+        self.current_spill_byte_count = 0;
+        for entry in &entries {
+            match entry.data {
+                Data::Memory(_) => self.current_byte_count += entry.byte_len,
+                Data::Spilled(_) => self.current_spill_byte_count += entry.byte_len,
+            }
+        }
+        self.current_entry_count = entries.len();
+    }
+
+    fn entry_location(&self, entry: &Self::CacheEntry) -> EntryLocation {
+        match entry.data {
+            Data::Memory(_) => EntryLocation::Memory,
+            Data::Spilled(_) => EntryLocation::Spilled,
+        }
+    }
+
+    fn entry_path<'a>(&self, entry: &'a Self::CacheEntry) -> Option<(&'a Path, u64)> {
+        match &entry.data {
+            Data::Memory(_) => None,
+            Data::Spilled(SpillFile(path)) => Some((path, 0)),
+        }
     }
 
     fn get_cache_capacity(&self) -> Option<CacheCapacity> {
         self.byte_limit
             .map(|byte_limit| CacheCapacity::new(byte_limit, self.current_byte_count))
     }
+
+    fn tier_capacity(&self, tier: Tier) -> Option<CacheCapacity> {
+        match tier {
+            Tier::Memory => self.get_cache_capacity(),
+            Tier::Disk => self.spill_byte_limit.map(|spill_byte_limit| {
+                CacheCapacity::new(spill_byte_limit, self.current_spill_byte_count)
+            }),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Memory, LIMIT_KIND_BYTE, LIMIT_KIND_ENTRY};
-    use crate::{async_test, Cache, Error, NO_COMPRESSION};
+    use super::{Memory, LIMIT_KIND_BYTE, LIMIT_KIND_ENTRY, LIMIT_KIND_SPILL_BYTE};
+    use crate::{
+        async_test, utils::test::TempDir, Cache, EntryLocation, Error, OnConflict, NO_COMPRESSION,
+    };
+    use std::time::Duration;
 
     async_test! {
         async fn test_default_strategy() {
@@ -173,5 +471,303 @@ mod tests {
                 }
             }
         }
+
+        async fn test_get_owned() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+
+            // The owned value can be held across a mutable call
+            let foo = cache.get_owned("foo").await.unwrap();
+            cache.put("bar", foo).await.unwrap();
+
+            assert_eq!(cache.get("bar").await.unwrap(), b"foo".as_slice());
+        }
+
+        async fn test_contains() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"bar".to_vec()).await.unwrap();
+
+            assert!(cache.contains_all(&["foo", "bar"]));
+            assert!(!cache.contains_all(&["foo", "baz"]));
+            assert!(cache.contains_any(&["foo", "baz"]));
+            assert!(!cache.contains_any(&["baz", "qux"]));
+            assert_eq!(cache.missing_keys(&["foo", "baz", "qux"]), vec![&"baz", &"qux"]);
+        }
+
+        async fn test_entries_by_size() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("bar", b"barbar".to_vec()).await.unwrap();
+            cache.put("baz", b"".to_vec()).await.unwrap();
+
+            assert_eq!(cache.entries_by_size(), vec![(&"bar", 6), (&"foo", 3), (&"baz", 0)]);
+        }
+
+        async fn test_get_if_fresh() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+
+            let value = cache.get_if_fresh("foo", Duration::from_secs(60)).await.unwrap();
+            assert_eq!(value.unwrap(), b"foo".as_slice());
+
+            std::thread::sleep(Duration::from_millis(20));
+            let value = cache.get_if_fresh("foo", Duration::from_millis(10)).await.unwrap();
+            assert!(value.is_none());
+
+            assert!(cache.get_if_fresh("bar", Duration::from_secs(60)).await.is_err());
+        }
+
+        async fn test_extend() {
+            let mut cache = Cache::new(Memory::new(None, Some(2)), NO_COMPRESSION).await.unwrap();
+
+            let entries = vec![
+                ("foo", b"foo".to_vec()),
+                ("bar", b"bar".to_vec()),
+                ("baz", b"baz".to_vec()),
+            ];
+            let inserted = cache.extend(entries).await.unwrap();
+
+            assert_eq!(inserted, 2);
+            assert!(cache.exists("foo"));
+            assert!(cache.exists("bar"));
+            assert!(!cache.exists("baz"));
+        }
+
+        async fn test_get_or_default() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+
+            assert_eq!(cache.get_or_default("foo").await.unwrap(), b"foo".as_slice());
+            assert!(cache.get_or_default("bar").await.unwrap().is_empty());
+        }
+
+        async fn test_remove() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+
+            assert!(cache.remove("foo").await.unwrap());
+            assert!(!cache.exists("foo"));
+            assert!(!cache.remove("foo").await.unwrap());
+        }
+
+        async fn test_is_persistent() {
+            let cache = Cache::<&str, _, _>::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+            assert!(!cache.is_persistent());
+        }
+
+        async fn test_try_get() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            cache.put("empty", Vec::new()).await.unwrap();
+
+            assert_eq!(cache.try_get("foo").await.unwrap().unwrap(), b"foo".as_slice());
+            assert!(cache.try_get("empty").await.unwrap().unwrap().is_empty());
+            assert!(cache.try_get("bar").await.unwrap().is_none());
+        }
+
+        async fn test_get_shared() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+
+            let first = cache.get_shared("foo").await.unwrap();
+            let second = cache.get_shared("foo").await.unwrap();
+            assert_eq!(&*first, b"foo");
+            assert!(std::sync::Arc::ptr_eq(&first, &second));
+        }
+
+        async fn test_put_with_policy() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+            assert!(cache.put_with_policy("foo", b"foo".as_slice(), OnConflict::Reject).await.unwrap());
+
+            let result = cache.put_with_policy("foo", b"bar".as_slice(), OnConflict::Reject).await;
+            assert!(matches!(result, Err(Error::KeyExists)));
+            assert!(!cache.put_with_policy("foo", b"bar".as_slice(), OnConflict::KeepExisting).await.unwrap());
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+
+            assert!(cache.put_with_policy("foo", b"bar".as_slice(), OnConflict::Overwrite).await.unwrap());
+            assert_eq!(cache.get("foo").await.unwrap(), b"bar".as_slice());
+        }
+
+        async fn test_append() {
+            let mut cache = Cache::new(Memory::new(Some(8), None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+
+            cache.append("foo", b"bar").await.unwrap();
+            assert_eq!(cache.get("foo").await.unwrap(), b"foobar".as_slice());
+            assert_eq!(cache.strategy().current_byte_count, 6);
+            assert_eq!(cache.strategy().current_entry_count, 1);
+
+            let result = cache.append("foo", b"baz").await;
+            assert!(matches!(result, Err(Error::LimitExceeded { limit_kind }) if limit_kind == LIMIT_KIND_BYTE));
+            assert_eq!(cache.get("foo").await.unwrap(), b"foobar".as_slice());
+
+            assert!(matches!(cache.append("bar", b"bar").await, Err(Error::KeyNotFound)));
+            cache.append_or_create("bar", b"ba").await.unwrap();
+            assert_eq!(cache.get("bar").await.unwrap(), b"ba".as_slice());
+            assert_eq!(cache.strategy().current_byte_count, 8);
+        }
+
+        async fn test_put_if_absent() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+
+            assert!(cache.put_if_absent("foo", b"foo".as_slice()).await.unwrap());
+            assert!(!cache.put_if_absent("foo", b"bar".as_slice()).await.unwrap());
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+            assert_eq!(cache.strategy().current_byte_count, 3);
+            assert_eq!(cache.strategy().current_entry_count, 1);
+        }
+
+        async fn test_get_or_insert_with() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+
+            let value = cache.get_or_insert_with("foo", || async { b"foo".to_vec() }).await.unwrap();
+            assert_eq!(value, b"foo".as_slice());
+            let value = cache.get_or_insert_with("foo", || async { b"bar".to_vec() }).await.unwrap();
+            assert_eq!(value, b"foo".as_slice());
+            assert_eq!(cache.strategy().current_entry_count, 1);
+        }
+
+        async fn test_get_or_try_insert_with() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+
+            let result = cache
+                .get_or_try_insert_with("foo", || async {
+                    Err(std::io::Error::new(std::io::ErrorKind::Other, "unavailable"))
+                })
+                .await;
+            assert!(matches!(result, Err(Error::CustomError(_))));
+            assert!(cache.get("foo").await.is_err());
+            assert_eq!(cache.strategy().current_entry_count, 0);
+
+            let value = cache
+                .get_or_try_insert_with("foo", || async { Ok::<_, std::io::Error>(b"foo".to_vec()) })
+                .await
+                .unwrap();
+            assert_eq!(value, b"foo".as_slice());
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+        }
+
+        async fn test_spill_oversized() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(
+                Memory::new(Some(4), None).with_spill_dir(temp_dir.as_ref()),
+                NO_COMPRESSION,
+            ).await.unwrap();
+
+            cache.put("big", b"big value".to_vec()).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+            assert_eq!(cache.strategy().current_byte_count, 3);
+            assert_eq!(cache.strategy().current_entry_count, 2);
+
+            // Spilled files don't survive a restart
+            assert_eq!(cache.get_detailed("big").await.unwrap().1.location(), EntryLocation::Spilled);
+            assert!(!cache.is_durable("big"));
+            assert!(!cache.is_durable("foo"));
+
+            // Values fitting the limit are still rejected once it's reached
+            let result = cache.put("bar", b"bar".to_vec()).await;
+            assert!(matches!(result, Err(Error::LimitExceeded { limit_kind }) if limit_kind == LIMIT_KIND_BYTE));
+
+            assert_eq!(cache.get("big").await.unwrap(), b"big value".as_slice());
+            assert_eq!(cache.get_shared("big").await.unwrap().as_ref(), b"big value");
+            assert!(cache.verify().await.unwrap().is_empty());
+
+            let (path, offset) = cache.entry_path("big").unwrap();
+            assert_eq!(offset, 0);
+            assert!(path.exists());
+            cache.delete("big").await.unwrap();
+            assert!(!path.exists());
+            assert_eq!(cache.strategy().current_byte_count, 3);
+            assert_eq!(cache.strategy().current_entry_count, 1);
+        }
+
+        async fn test_spill_byte_limit() {
+            let temp_dir = TempDir::new();
+            let mut cache = Cache::new(
+                Memory::new(Some(4), None)
+                    .with_spill_dir(temp_dir.as_ref())
+                    .with_spill_byte_limit(12),
+                NO_COMPRESSION,
+            ).await.unwrap();
+
+            cache.put("foo", b"foo value".to_vec()).await.unwrap();
+            let result = cache.put("bar", b"bar value".to_vec()).await;
+            assert!(matches!(result, Err(Error::LimitExceeded { limit_kind }) if limit_kind == LIMIT_KIND_SPILL_BYTE));
+
+            // Overwriting a spilled value writes a new file, and only removes the old
+            // one once that succeeded
+            let (old_path, _) = cache.entry_path("foo").unwrap();
+            cache.put("foo", b"new value".to_vec()).await.unwrap();
+            let (new_path, _) = cache.entry_path("foo").unwrap();
+            assert_ne!(old_path, new_path);
+            assert!(!old_path.exists());
+            assert_eq!(cache.get("foo").await.unwrap(), b"new value".as_slice());
+            assert_eq!(cache.strategy().current_spill_byte_count, 9);
+
+            assert_eq!(cache.take("foo").await.unwrap(), b"new value".to_vec());
+            assert!(!new_path.exists());
+            assert_eq!(cache.strategy().current_spill_byte_count, 0);
+            assert_eq!(cache.strategy().current_entry_count, 0);
+        }
+
+        async fn test_failed_overwrite_keeps_value() {
+            let mut cache = Cache::new(Memory::new(Some(3), None), NO_COMPRESSION).await.unwrap();
+            cache.put("foo", b"foo".to_vec()).await.unwrap();
+
+            // The old entry's space counts as free
+            cache.put("foo", b"bar".to_vec()).await.unwrap();
+
+            let result = cache.put("foo", b"foobar".to_vec()).await;
+            assert!(matches!(result, Err(Error::LimitExceeded { .. })));
+            assert_eq!(cache.get("foo").await.unwrap(), b"bar".as_slice());
+            assert_eq!(cache.strategy().current_byte_count, 3);
+            assert_eq!(cache.strategy().current_entry_count, 1);
+        }
+
+
+        async fn test_spill_files_removed() {
+            let temp_dir = TempDir::new();
+            let spill_path = {
+                let mut cache = Cache::new(
+                    Memory::new(Some(4), None).with_spill_dir(temp_dir.as_ref()),
+                    NO_COMPRESSION,
+                ).await.unwrap();
+                cache.put("big", b"big value".to_vec()).await.unwrap();
+                cache.entry_path("big").unwrap().0
+            };
+
+            // Dropping the cache removes its spill files
+            assert!(!spill_path.exists());
+
+            // Spill files left behind by other processes are removed on setup
+            let leftover = temp_dir.as_ref().join(format!("bincache-{}-0.spill", std::process::id() + 1));
+            std::fs::write(&leftover, b"big value").unwrap();
+            let other = temp_dir.as_ref().join("other");
+            std::fs::write(&other, b"other").unwrap();
+            let cache = Cache::<&str, _, _>::new(
+                Memory::new(Some(4), None).with_spill_dir(temp_dir.as_ref()),
+                NO_COMPRESSION,
+            ).await.unwrap();
+            assert!(!leftover.exists());
+            assert!(other.exists());
+
+            cache.close().await.unwrap();
+        }
+    }
+
+    #[cfg(feature = "bytes")]
+    async_test! {
+        async fn test_put_bytes() {
+            let mut cache = Cache::new(Memory::default(), NO_COMPRESSION).await.unwrap();
+
+            cache.put_bytes("foo", bytes::Bytes::from_static(b"foo")).await.unwrap();
+
+            assert_eq!(cache.get("foo").await.unwrap(), b"foo".as_slice());
+            assert_eq!(cache.strategy().current_byte_count, 3);
+        }
     }
 }
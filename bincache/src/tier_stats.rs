@@ -0,0 +1,32 @@
+/// Per-tier key and byte counts of a [Cache](crate::Cache).
+///
+/// See [Cache::tier_stats](crate::Cache::tier_stats).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TierStats {
+    pub(crate) memory_keys: usize,
+    pub(crate) disk_keys: usize,
+    pub(crate) memory_bytes: usize,
+    pub(crate) disk_bytes: usize,
+}
+
+impl TierStats {
+    /// Get the number of keys stored in memory.
+    pub fn memory_keys(&self) -> usize {
+        self.memory_keys
+    }
+
+    /// Get the number of keys stored on disk.
+    pub fn disk_keys(&self) -> usize {
+        self.disk_keys
+    }
+
+    /// Get the number of bytes stored in memory.
+    pub fn memory_bytes(&self) -> usize {
+        self.memory_bytes
+    }
+
+    /// Get the number of bytes stored on disk.
+    pub fn disk_bytes(&self) -> usize {
+        self.disk_bytes
+    }
+}
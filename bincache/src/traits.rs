@@ -4,8 +4,8 @@ mod compression_strategy;
 mod flushable_strategy;
 mod recoverable_strategy;
 
-pub use cache_key::CacheKey;
-pub use cache_strategy::CacheStrategy;
+pub use cache_key::{CacheKey, KeyEncoding};
+pub use cache_strategy::{CacheStrategy, EntryLocation, Tier};
 pub use compression_strategy::CompressionStrategy;
 pub use flushable_strategy::FlushableStrategy;
 pub use recoverable_strategy::RecoverableStrategy;
@@ -1,9 +1,52 @@
+use std::fmt::Write;
+
 /// A cache key.
 ///
 /// Keys should be unique and deterministic.
 /// The same key should always return the same value.
 pub trait CacheKey {
     fn to_key(&self) -> String;
+
+    /// Get the raw bytes of the key.
+    ///
+    /// Used instead of [to_key](CacheKey::to_key) when keys are encoded with
+    /// [KeyEncoding::Hex]. Binary keys should override this to return their bytes,
+    /// which avoids collisions between keys with the same string representation.
+    fn to_key_bytes(&self) -> Vec<u8> {
+        self.to_key().into_bytes()
+    }
+}
+
+/// How keys are encoded when used as file names.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEncoding {
+    /// Use [CacheKey::to_key] as-is.
+    #[default]
+    Plain,
+    /// Hex-encode [CacheKey::to_key_bytes].
+    ///
+    /// Every key maps to a distinct, file-system safe name.
+    Hex,
+}
+
+impl KeyEncoding {
+    /// Encode a key.
+    pub fn encode<K>(&self, key: &K) -> String
+    where
+        K: CacheKey + ?Sized,
+    {
+        match self {
+            KeyEncoding::Plain => key.to_key(),
+            KeyEncoding::Hex => {
+                let bytes = key.to_key_bytes();
+                let mut encoded = String::with_capacity(bytes.len() * 2);
+                for byte in bytes {
+                    _ = write!(encoded, "{byte:02x}");
+                }
+                encoded
+            }
+        }
+    }
 }
 
 // Blanket implementation for all types that implement `ToString`
@@ -15,3 +58,27 @@ where
         self.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheKey, KeyEncoding};
+
+    struct BinaryKey([u8; 2]);
+
+    impl CacheKey for BinaryKey {
+        fn to_key(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn to_key_bytes(&self) -> Vec<u8> {
+            self.0.to_vec()
+        }
+    }
+
+    #[test]
+    fn test_key_encoding() {
+        assert_eq!(KeyEncoding::Plain.encode("foo"), "foo");
+        assert_eq!(KeyEncoding::Hex.encode("foo"), "666f6f");
+        assert_eq!(KeyEncoding::Hex.encode(&BinaryKey([0x00, 0xff])), "00ff");
+    }
+}
@@ -1,10 +1,55 @@
 use async_trait::async_trait;
-use std::borrow::Cow;
+use std::{borrow::Cow, path::Path, sync::Arc};
 
-use crate::{CacheCapacity, Result};
+use crate::{entry_header::EntryHeader, CacheCapacity, ReadBufferStats, Result};
 
 use super::CacheKey;
 
+/// Where an entry is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EntryLocation {
+    /// The entry is stored in memory.
+    Memory,
+    /// The entry is stored on disk.
+    Disk,
+    /// The entry is stored in memory and has a copy on disk.
+    MemoryAndDisk,
+    /// The entry is stored in a temporary file on disk, which doesn't survive a restart.
+    Spilled,
+    /// The strategy doesn't report where the entry is stored.
+    Unknown,
+}
+
+impl EntryLocation {
+    /// Returns true if the entry is held in memory.
+    pub fn is_in_memory(self) -> bool {
+        matches!(self, EntryLocation::Memory | EntryLocation::MemoryAndDisk)
+    }
+
+    /// Returns true if the entry has a copy on disk.
+    pub fn is_on_disk(self) -> bool {
+        matches!(
+            self,
+            EntryLocation::Disk | EntryLocation::MemoryAndDisk | EntryLocation::Spilled
+        )
+    }
+
+    /// Returns true if the entry has a copy on disk that survives a restart.
+    pub fn is_durable(self) -> bool {
+        matches!(self, EntryLocation::Disk | EntryLocation::MemoryAndDisk)
+    }
+}
+
+/// A storage tier of a cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    /// The in-memory tier.
+    Memory,
+    /// The on-disk tier.
+    Disk,
+}
+
 /// A cache strategy.
 #[async_trait]
 pub trait CacheStrategy {
@@ -17,12 +62,92 @@ pub trait CacheStrategy {
         Ok(())
     }
 
+    /// Tear down the cache, e.g. to release connections or sync pending data.
+    ///
+    /// Called by [Cache::close](crate::Cache::close). Stored entries are kept,
+    /// so they can be recovered later.
+    async fn teardown(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     /// Put a value into the cache.
     async fn put<'a, K, V>(&mut self, key: &K, value: V) -> Result<Self::CacheEntry>
     where
         K: CacheKey + Sync + Send,
         V: Into<Cow<'a, [u8]>> + Send;
 
+    /// Put a value into the cache along with a header describing it.
+    ///
+    /// Strategies storing entries in files write the header in front of the value,
+    /// so it can be read back on recovery. By default, the header is dropped.
+    async fn put_with_header<'a, K, V>(
+        &mut self,
+        key: &K,
+        value: V,
+        header: &EntryHeader,
+    ) -> Result<Self::CacheEntry>
+    where
+        K: CacheKey + Sync + Send,
+        V: Into<Cow<'a, [u8]>> + Send,
+    {
+        _ = header;
+        self.put(key, value).await
+    }
+
+    /// Replace the value of an existing entry, returning the new entry.
+    ///
+    /// The space `old` occupies counts as free while storing the new value. On
+    /// success, `old` no longer counts towards the limits and its data is gone,
+    /// so it must be dropped instead of being deleted. On failure, `old` is left intact.
+    ///
+    /// By default, replacing isn't supported and [Error::Unsupported](crate::Error::Unsupported)
+    /// is returned. The cache then deletes the old entry before putting the new one.
+    ///
+    /// `old` is borrowed across await points, so entries have to be `Sync` to be replaced.
+    async fn replace<'a, K, V>(
+        &mut self,
+        key: &K,
+        old: &Self::CacheEntry,
+        value: V,
+    ) -> Result<Self::CacheEntry>
+    where
+        K: CacheKey + Sync + Send,
+        V: Into<Cow<'a, [u8]>> + Send,
+        Self::CacheEntry: Sync,
+    {
+        _ = (key, old, value);
+        Err(crate::Error::Unsupported {
+            operation: "replace",
+        })
+    }
+
+    /// Replace the value of an existing entry along with its header, see
+    /// [replace](Self::replace) and [put_with_header](Self::put_with_header).
+    async fn replace_with_header<'a, K, V>(
+        &mut self,
+        key: &K,
+        old: &Self::CacheEntry,
+        value: V,
+        header: &EntryHeader,
+    ) -> Result<Self::CacheEntry>
+    where
+        K: CacheKey + Sync + Send,
+        V: Into<Cow<'a, [u8]>> + Send,
+        Self::CacheEntry: Sync,
+    {
+        _ = header;
+        self.replace(key, old, value).await
+    }
+
+    /// Get the header stored with an entry.
+    ///
+    /// Returns `None` if the strategy doesn't store headers, or the entry was
+    /// stored without one.
+    fn entry_header(&self, entry: &Self::CacheEntry) -> Option<EntryHeader> {
+        _ = entry;
+        None
+    }
+
     /// Get a value from the cache.
     async fn get<'a>(&self, entry: &'a Self::CacheEntry) -> Result<Cow<'a, [u8]>>;
 
@@ -32,6 +157,126 @@ pub trait CacheStrategy {
     /// Delete a value from the cache.
     async fn delete(&mut self, entry: Self::CacheEntry) -> Result<()>;
 
+    /// Append data to an entry's value in place.
+    ///
+    /// By default, appending isn't supported and [Error::Unsupported](crate::Error::Unsupported)
+    /// is returned.
+    async fn append(&mut self, entry: &mut Self::CacheEntry, data: &[u8]) -> Result<()>
+    where
+        Self::CacheEntry: Send,
+    {
+        _ = (entry, data);
+        Err(crate::Error::Unsupported {
+            operation: "append",
+        })
+    }
+
+    /// Get a value as a shared buffer, without copying it.
+    ///
+    /// Returns `None` if the strategy doesn't store values in shared buffers.
+    fn get_shared(&self, entry: &Self::CacheEntry) -> Option<Arc<[u8]>> {
+        _ = entry;
+        None
+    }
+
+    /// Verify that the data backing an entry is intact.
+    ///
+    /// Returns `false` if the data is missing or doesn't match the recorded size.
+    async fn verify(&self, entry: &Self::CacheEntry) -> Result<bool> {
+        _ = entry;
+        Ok(true)
+    }
+
+    /// Get the number of bytes an entry occupies in the cache.
+    ///
+    /// Returns `None` if the strategy doesn't track entry sizes.
+    fn stored_byte_len(&self, entry: &Self::CacheEntry) -> Option<usize> {
+        _ = entry;
+        None
+    }
+
+    /// Recompute the strategy's usage counters from all of its entries, e.g. after
+    /// they drifted from the stored data.
+    ///
+    /// By default, nothing is done.
+    fn recount(&mut self, entries: Vec<&Self::CacheEntry>) {
+        _ = entries;
+    }
+
+    /// Get the number of bytes each entry occupies in addition to its data,
+    /// e.g. for file system metadata.
+    ///
+    /// The overhead counts towards the byte limit and the reported capacity.
+    fn entry_overhead(&self) -> usize {
+        0
+    }
+
+    /// Get where an entry is stored.
+    fn entry_location(&self, entry: &Self::CacheEntry) -> EntryLocation {
+        _ = entry;
+        EntryLocation::Unknown
+    }
+
+    /// Get the path of the file holding an entry's data, along with the offset
+    /// the data starts at, e.g. after a header.
+    ///
+    /// Returns `None` if the entry isn't stored in a file, or if the strategy
+    /// transforms the data so the file can't be used as-is.
+    fn entry_path<'a>(&self, entry: &'a Self::CacheEntry) -> Option<(&'a Path, u64)> {
+        _ = entry;
+        None
+    }
+
+    /// Get the directory entries are stored in.
+    ///
+    /// Returns `None` if the strategy doesn't store entries on disk.
+    fn cache_dir(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Whether entries survive a restart, e.g. because they are stored on disk.
+    ///
+    /// Strategies that keep some entries in memory, like
+    /// [Hybrid](crate::strategies::Hybrid), are still considered persistent.
+    fn is_persistent(&self) -> bool {
+        false
+    }
+
     /// Get cache capacity. Returns None if no limit was set.
     fn get_cache_capacity(&self) -> Option<CacheCapacity>;
+
+    /// Get the hit and miss counts of the strategy's read buffer.
+    ///
+    /// Returns None if the strategy doesn't buffer reads.
+    fn read_buffer_stats(&self) -> Option<ReadBufferStats> {
+        None
+    }
+
+    /// Move an entry held in memory to a lower tier, e.g. to disk.
+    ///
+    /// Used by the cache to enforce its
+    /// [flush high watermark](crate::cache_builder::CacheBuilderWithStrategy::flush_high_watermark).
+    /// Returns the entry replacing `entry`, or `None` if it can't be moved. On success,
+    /// `entry` no longer counts towards the memory limit, so it must be dropped
+    /// instead of being deleted. By default, entries aren't moved.
+    async fn demote<K>(
+        &mut self,
+        key: &K,
+        entry: &Self::CacheEntry,
+    ) -> Result<Option<Self::CacheEntry>>
+    where
+        K: CacheKey + Sync + Send,
+        Self::CacheEntry: Sync,
+    {
+        _ = (key, entry);
+        Ok(None)
+    }
+
+    /// Get the capacity of a single storage tier.
+    ///
+    /// Returns None if the strategy doesn't store entries in `tier` or no limit was set for it.
+    fn tier_capacity(&self, tier: Tier) -> Option<CacheCapacity> {
+        _ = tier;
+        None
+    }
 }
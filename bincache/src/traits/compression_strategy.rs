@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use crate::Result;
+use crate::{compression::CompressionLevel, Result};
 use async_trait::async_trait;
 
 /// A compression strategy.
@@ -8,6 +8,20 @@ use async_trait::async_trait;
 pub trait CompressionStrategy: std::fmt::Debug {
     /// Compress binary data
     async fn compress<'a>(&self, data: Cow<'a, [u8]>) -> Result<Cow<'a, [u8]>>;
+    /// Compress binary data with a specific compression level
+    ///
+    /// By default, the level is ignored and the data is compressed like [compress](Self::compress).
+    async fn compress_with_level<'a>(
+        &self,
+        data: Cow<'a, [u8]>,
+        level: CompressionLevel,
+    ) -> Result<Cow<'a, [u8]>>
+    where
+        Self: Sync,
+    {
+        _ = level;
+        self.compress(data).await
+    }
     /// Decompress binary data
     async fn decompress<'a>(&self, value: Cow<'a, [u8]>) -> Result<Cow<'a, [u8]>>;
 }
@@ -21,6 +35,17 @@ impl<T: CompressionStrategy + Sync + Send> CompressionStrategy for Option<T> {
         }
     }
 
+    async fn compress_with_level<'a>(
+        &self,
+        data: Cow<'a, [u8]>,
+        level: CompressionLevel,
+    ) -> Result<Cow<'a, [u8]>> {
+        match self {
+            Some(compressor) => compressor.compress_with_level(data, level).await,
+            None => Ok(data),
+        }
+    }
+
     async fn decompress<'a>(&self, value: Cow<'a, [u8]>) -> Result<Cow<'a, [u8]>> {
         match self {
             Some(compressor) => compressor.decompress(value).await,
@@ -28,3 +53,22 @@ impl<T: CompressionStrategy + Sync + Send> CompressionStrategy for Option<T> {
         }
     }
 }
+
+#[async_trait]
+impl<T: CompressionStrategy + Sync + Send + ?Sized> CompressionStrategy for Box<T> {
+    async fn compress<'a>(&self, data: Cow<'a, [u8]>) -> Result<Cow<'a, [u8]>> {
+        self.as_ref().compress(data).await
+    }
+
+    async fn compress_with_level<'a>(
+        &self,
+        data: Cow<'a, [u8]>,
+        level: CompressionLevel,
+    ) -> Result<Cow<'a, [u8]>> {
+        self.as_ref().compress_with_level(data, level).await
+    }
+
+    async fn decompress<'a>(&self, value: Cow<'a, [u8]>) -> Result<Cow<'a, [u8]>> {
+        self.as_ref().decompress(value).await
+    }
+}
@@ -6,11 +6,44 @@ use crate::Result;
 /// A cache strategy that can flush its data to a non-volatile storage.
 #[async_trait]
 pub trait FlushableStrategy: CacheStrategy {
+    /// Flush an entry to a non-volatile storage.
+    ///
+    /// Returns the entry replacing the flushed one, or `None` if the entry
+    /// doesn't need to be flushed. By default, no entry is flushed.
     async fn flush<K>(
         &mut self,
         key: &K,
         entry: &Self::CacheEntry,
     ) -> Result<Option<Self::CacheEntry>>
     where
-        K: CacheKey + Sync + Send;
+        K: CacheKey + Sync + Send,
+        Self::CacheEntry: Sync,
+    {
+        _ = (key, entry);
+        Ok(None)
+    }
+
+    /// Write an entry to a non-volatile storage, keeping the original in place.
+    ///
+    /// The entry is updated to track both copies. Returns whether a copy was written,
+    /// or `false` if the entry doesn't need to be copied. By default, no entry is copied.
+    async fn flush_copy<K>(&mut self, key: &K, entry: &mut Self::CacheEntry) -> Result<bool>
+    where
+        K: CacheKey + Sync + Send,
+        Self::CacheEntry: Send,
+    {
+        _ = (key, entry);
+        Ok(false)
+    }
+
+    /// Release an entry that was replaced by its [flushed](Self::flush) counterpart.
+    ///
+    /// Unlike [delete](CacheStrategy::delete), this must keep any data the flushed
+    /// entry still refers to. By default, the entry is deleted.
+    async fn release_flushed(&mut self, entry: Self::CacheEntry) -> Result<()>
+    where
+        Self::CacheEntry: Send,
+    {
+        self.delete(entry).await
+    }
 }
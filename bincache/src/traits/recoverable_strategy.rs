@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 
 use super::CacheStrategy;
-use crate::Result;
+use crate::{RecoveryReport, Result};
 
 /// A cache strategy that can recover its data from a non-volatile storage.
 #[async_trait]
@@ -15,4 +15,72 @@ pub trait RecoverableStrategy: CacheStrategy {
         _ = recover_key;
         Ok(vec![])
     }
+
+    /// Attempt to recover the cache from a crash, only considering stored
+    /// entries whose name passes `should_consider`.
+    ///
+    /// Entries failing the predicate are left untouched.
+    async fn recover_filtered<K, F, P>(
+        &mut self,
+        recover_key: F,
+        should_consider: P,
+    ) -> Result<Vec<(K, Self::CacheEntry)>>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+        P: Fn(&str) -> bool + Send,
+    {
+        _ = (recover_key, should_consider);
+        Ok(vec![])
+    }
+
+    /// Attempt to recover the cache from a crash, reporting what happened to the stored data.
+    ///
+    /// Stored entries that can't be read are skipped. The number of recovered
+    /// entries is filled in by the [Cache](crate::Cache). By default, this is the
+    /// same as [recover](Self::recover) with an empty report.
+    async fn recover_with_report<K, F>(
+        &mut self,
+        recover_key: F,
+    ) -> Result<(Vec<(K, Self::CacheEntry)>, RecoveryReport)>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        let entries = self.recover(recover_key).await?;
+        Ok((entries, RecoveryReport::default()))
+    }
+
+    /// Attempt to recover the cache from a crash, without reading the stored data.
+    ///
+    /// By default, this is the same as [recover](Self::recover).
+    async fn recover_fast<K, F>(&mut self, recover_key: F) -> Result<Vec<(K, Self::CacheEntry)>>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        self.recover(recover_key).await
+    }
+
+    /// Attempt to recover entries that a previous recovery couldn't identify,
+    /// e.g. after the key recovery logic has been fixed.
+    async fn recover_lost_found<K, F>(
+        &mut self,
+        recover_key: F,
+    ) -> Result<Vec<(K, Self::CacheEntry)>>
+    where
+        K: Send,
+        F: Fn(&str) -> Option<K> + Send,
+    {
+        _ = recover_key;
+        Ok(vec![])
+    }
+
+    /// Stop tracking a recovered entry without touching its stored data.
+    ///
+    /// Called when recovery finds an entry for a key that is already indexed,
+    /// and both refer to the same stored data.
+    fn forget(&mut self, entry: Self::CacheEntry) {
+        _ = entry;
+    }
 }
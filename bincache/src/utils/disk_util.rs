@@ -1,8 +1,35 @@
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
-use crate::Result;
+use crate::{
+    entry_header::{self, EntryHeader},
+    RecoveryReport, Result,
+};
 
-pub async fn create_dir(path: impl AsRef<Path>) -> Result<()> {
+/// The maximum length of a file name in bytes, as limited by most file systems.
+pub const MAX_FILE_NAME_LEN: usize = 255;
+
+/// Get the path of an entry file named `file_name` in `dir`.
+///
+/// Returns [Error::KeyTooLong](crate::Error::KeyTooLong) if the name exceeds
+/// [MAX_FILE_NAME_LEN], instead of the opaque I/O error writing it would cause.
+pub fn entry_path(dir: &Path, file_name: String) -> Result<PathBuf> {
+    if file_name.len() > MAX_FILE_NAME_LEN {
+        return Err(crate::Error::KeyTooLong {
+            len: file_name.len(),
+            limit: MAX_FILE_NAME_LEN,
+        });
+    }
+    Ok(dir.join(file_name))
+}
+
+/// Create a directory and all of its parents.
+///
+/// If `mode` is set, the permissions of the leaf directory are set to it (Unix only).
+pub async fn create_dir(path: impl AsRef<Path>, mode: Option<u32>) -> Result<()> {
     #[cfg(any(
         feature = "blocking",
         all(
@@ -12,23 +39,69 @@ pub async fn create_dir(path: impl AsRef<Path>) -> Result<()> {
     ))]
     {
         use std::fs::create_dir_all;
-        Ok(create_dir_all(&path)?)
+        create_dir_all(&path)?;
     }
     #[cfg(feature = "rt_tokio_1")]
     {
         use tokio::fs::create_dir_all;
-        Ok(create_dir_all(&path).await?)
+        create_dir_all(&path).await?;
     }
     #[cfg(feature = "rt_async-std_1")]
     {
         use async_std::fs::create_dir_all;
-        Ok(create_dir_all(path.as_ref()).await?)
+        create_dir_all(path.as_ref()).await?;
+    }
+
+    set_mode(path, mode).await
+}
+
+/// Check that a strategy's temporary directory isn't its cache directory.
+///
+/// Temporary files written there would be mistaken for entries by recovery.
+/// Both directories must exist.
+pub fn check_temp_dir(cache_dir: &Path, temp_dir: &Path) -> Result<()> {
+    let canonical = |path: &Path| {
+        path.canonicalize()
+            .map_err(|source| crate::Error::SetupFailed {
+                path: path.to_path_buf(),
+                source,
+            })
+    };
+    if canonical(cache_dir)? == canonical(temp_dir)? {
+        return Err(crate::Error::SetupFailed {
+            path: temp_dir.to_path_buf(),
+            source: std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the temporary directory must differ from the cache directory",
+            ),
+        });
     }
+    Ok(())
 }
 
-pub async fn read(path: impl AsRef<Path>, byte_len: Option<usize>) -> Result<Vec<u8>> {
-    let mut buf = Vec::with_capacity(byte_len.unwrap_or(0));
+/// Create the directory of a strategy during setup.
+///
+/// Like [create_dir], but I/O errors are reported as [Error::SetupFailed](crate::Error::SetupFailed)
+/// along with the offending path.
+pub async fn setup_dir(path: impl AsRef<Path>, mode: Option<u32>) -> Result<()> {
+    create_dir(&path, mode).await.map_err(|err| match err {
+        crate::Error::IoError(source) => crate::Error::SetupFailed {
+            path: path.as_ref().to_path_buf(),
+            source,
+        },
+        err => err,
+    })
+}
 
+/// Read an entry file into memory.
+/// Returns the file's [EntryHeader], if it has one, along with the value following it.
+///
+/// `byte_len` is used to pre-allocate the buffer. If it's not known, the
+/// file size is used instead to avoid reallocating while reading.
+pub async fn read(
+    path: impl AsRef<Path>,
+    byte_len: Option<usize>,
+) -> Result<(Option<EntryHeader>, Vec<u8>)> {
     #[cfg(any(
         feature = "blocking",
         all(
@@ -36,33 +109,127 @@ pub async fn read(path: impl AsRef<Path>, byte_len: Option<usize>) -> Result<Vec
             not(any(feature = "rt_tokio_1", feature = "rt_async-std_1")),
         )
     ))]
-    {
+    let entry = {
         use std::{fs::File, io::Read};
 
+        // Without a hint, std's read_to_end already reserves room for the rest of the file
         let mut file = File::open(path)?;
+        let capacity = byte_len.unwrap_or_default();
+        let mut buf = Vec::with_capacity(capacity.saturating_add(entry_header::MAX_LEN));
+        (&mut file)
+            .take(entry_header::MAX_LEN as u64)
+            .read_to_end(&mut buf)?;
+        let header = strip_header(&mut buf);
         file.read_to_end(&mut buf)?;
-    }
+        (header, buf)
+    };
 
     #[cfg(feature = "rt_tokio_1")]
-    {
+    let entry = {
         use tokio::{fs::File, io::AsyncReadExt};
 
         let mut file = File::open(path).await?;
+        let capacity = match byte_len {
+            Some(byte_len) => byte_len,
+            None => file.metadata().await?.len() as usize,
+        };
+        let mut buf = Vec::with_capacity(capacity.saturating_add(entry_header::MAX_LEN));
+        (&mut file)
+            .take(entry_header::MAX_LEN as u64)
+            .read_to_end(&mut buf)
+            .await?;
+        let header = strip_header(&mut buf);
         file.read_to_end(&mut buf).await?;
-    }
+        (header, buf)
+    };
 
     #[cfg(feature = "rt_async-std_1")]
-    {
+    let entry = {
         use async_std::{fs::File, io::ReadExt};
 
         let mut file = File::open(path.as_ref()).await?;
+        let capacity = match byte_len {
+            Some(byte_len) => byte_len,
+            None => file.metadata().await?.len() as usize,
+        };
+        let mut buf = Vec::with_capacity(capacity.saturating_add(entry_header::MAX_LEN));
+        (&mut file)
+            .take(entry_header::MAX_LEN as u64)
+            .read_to_end(&mut buf)
+            .await?;
+        let header = strip_header(&mut buf);
         file.read_to_end(&mut buf).await?;
-    }
+        (header, buf)
+    };
 
-    Ok(buf)
+    Ok(entry)
 }
 
-pub async fn write(path: impl AsRef<Path>, value: &[u8]) -> Result<()> {
+/// Read the [EntryHeader] of an entry file, if it has one, without reading its value.
+/// Returns the header along with the length of the value.
+pub async fn read_header(path: impl AsRef<Path>) -> Result<(Option<EntryHeader>, usize)> {
+    #[cfg(any(
+        feature = "blocking",
+        all(
+            feature = "implicit-blocking",
+            not(any(feature = "rt_tokio_1", feature = "rt_async-std_1")),
+        )
+    ))]
+    let (file_len, mut buf) = {
+        use std::{fs::File, io::Read};
+
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut buf = Vec::with_capacity(entry_header::MAX_LEN);
+        file.take(entry_header::MAX_LEN as u64)
+            .read_to_end(&mut buf)?;
+        (file_len, buf)
+    };
+
+    #[cfg(feature = "rt_tokio_1")]
+    let (file_len, mut buf) = {
+        use tokio::{fs::File, io::AsyncReadExt};
+
+        let file = File::open(path).await?;
+        let file_len = file.metadata().await?.len();
+        let mut buf = Vec::with_capacity(entry_header::MAX_LEN);
+        file.take(entry_header::MAX_LEN as u64)
+            .read_to_end(&mut buf)
+            .await?;
+        (file_len, buf)
+    };
+
+    #[cfg(feature = "rt_async-std_1")]
+    let (file_len, mut buf) = {
+        use async_std::{fs::File, io::ReadExt};
+
+        let file = File::open(path.as_ref()).await?;
+        let file_len = file.metadata().await?.len();
+        let mut buf = Vec::with_capacity(entry_header::MAX_LEN);
+        file.take(entry_header::MAX_LEN as u64)
+            .read_to_end(&mut buf)
+            .await?;
+        (file_len, buf)
+    };
+
+    let header = strip_header(&mut buf);
+    let header_len = header.map_or(0, |header| header.encoded_len());
+    Ok((header, file_len.saturating_sub(header_len as u64) as usize))
+}
+
+/// Decode the [EntryHeader] at the start of `buf` and remove it, leaving only
+/// the value's bytes.
+///
+/// Returns `None` and leaves `buf` untouched if it doesn't start with a valid
+/// header, e.g. because the file was written before headers existed.
+fn strip_header(buf: &mut Vec<u8>) -> Option<EntryHeader> {
+    let (header, header_len) = EntryHeader::decode(buf).ok()?;
+    buf.drain(..header_len);
+    Some(header)
+}
+
+/// Append data to the end of an existing file.
+pub async fn append(path: impl AsRef<Path>, value: &[u8]) -> Result<()> {
     #[cfg(any(
         feature = "blocking",
         all(
@@ -71,26 +238,27 @@ pub async fn write(path: impl AsRef<Path>, value: &[u8]) -> Result<()> {
         )
     ))]
     {
-        use std::{fs::File, io::Write};
-        let mut file = File::create(path)?;
+        use std::{fs::OpenOptions, io::Write};
+
+        let mut file = OpenOptions::new().append(true).open(path)?;
         file.write_all(value)?;
         file.sync_data()?;
     }
 
     #[cfg(feature = "rt_tokio_1")]
     {
-        use tokio::{fs::File, io::AsyncWriteExt};
+        use tokio::{fs::OpenOptions, io::AsyncWriteExt};
 
-        let mut file = File::create(path).await?;
+        let mut file = OpenOptions::new().append(true).open(path).await?;
         file.write_all(value).await?;
         file.sync_data().await?;
     }
 
     #[cfg(feature = "rt_async-std_1")]
     {
-        use async_std::{fs::File, io::WriteExt};
+        use async_std::{fs::OpenOptions, io::WriteExt};
 
-        let mut file = File::create(path.as_ref()).await?;
+        let mut file = OpenOptions::new().append(true).open(path.as_ref()).await?;
         file.write_all(value).await?;
         file.sync_data().await?;
     }
@@ -98,6 +266,183 @@ pub async fn write(path: impl AsRef<Path>, value: &[u8]) -> Result<()> {
     Ok(())
 }
 
+/// The name of the directory inside a cache directory that entry files are
+/// written to before being moved into place, unless another one is configured.
+pub const TEMP_DIR_NAME: &str = ".tmp";
+
+/// Write an entry file atomically, by writing it to a temporary file in `temp_dir`
+/// first and renaming it into place. If given, the `header` is written in front
+/// of the value.
+///
+/// Each write uses its own temporary file, so concurrent writes of the same
+/// entry don't interfere. If `temp_dir` is on a different file system, the file
+/// is copied instead, which isn't atomic. Other rename errors are returned as is.
+/// If `temp_dir` is `None`, the temporary file is created next to `path`.
+///
+/// If `mode` is set, newly created files get these permissions (Unix only).
+///
+/// ## Cancellation
+/// The file is only moved into place once it has been written completely. If the
+/// returned future is dropped before that, the temporary file is removed and the
+/// file at `path` is left untouched.
+pub async fn write_atomic(
+    path: impl AsRef<Path>,
+    header: Option<&EntryHeader>,
+    value: &[u8],
+    mode: Option<u32>,
+    temp_dir: Option<&Path>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let temp_dir = match temp_dir {
+        Some(temp_dir) => temp_dir,
+        None => path.parent().unwrap_or_else(|| Path::new(".")),
+    };
+    let (temp_file, file) = TempFile::create(temp_dir, mode)?;
+    let header = header.map(EntryHeader::encode).unwrap_or_default();
+
+    #[cfg(any(
+        feature = "blocking",
+        all(
+            feature = "implicit-blocking",
+            not(any(feature = "rt_tokio_1", feature = "rt_async-std_1")),
+        )
+    ))]
+    {
+        use std::io::Write;
+
+        let mut file = file;
+        file.write_all(&header)?;
+        file.write_all(value)?;
+        file.sync_data()?;
+    }
+
+    #[cfg(feature = "rt_tokio_1")]
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let mut file = tokio::fs::File::from_std(file);
+        file.write_all(&header).await?;
+        file.write_all(value).await?;
+        file.sync_data().await?;
+    }
+
+    #[cfg(feature = "rt_async-std_1")]
+    {
+        use async_std::io::WriteExt;
+
+        let mut file = async_std::fs::File::from(file);
+        file.write_all(&header).await?;
+        file.write_all(value).await?;
+        file.sync_data().await?;
+    }
+
+    Ok(temp_file.persist(path)?)
+}
+
+/// A temporary file that is removed when dropped, unless it's persisted.
+///
+/// The file is opened and moved into place with blocking calls, so dropping a
+/// write halfway through can't leave either step running in the background.
+struct TempFile {
+    path: PathBuf,
+    persisted: bool,
+}
+
+impl TempFile {
+    /// Create a new temporary file with a unique name in `dir`, and open it for writing.
+    fn create(dir: &Path, mode: Option<u32>) -> std::io::Result<(Self, std::fs::File)> {
+        use std::fs::OpenOptions;
+
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(mode);
+        }
+
+        let path = dir.join(unique_file_name("tmp"));
+        let file = options.open(&path)?;
+        Ok((
+            Self {
+                path,
+                persisted: false,
+            },
+            file,
+        ))
+    }
+
+    /// Move the file to `path`, replacing the file there.
+    fn persist(mut self, path: &Path) -> std::io::Result<()> {
+        match std::fs::rename(&self.path, path) {
+            Ok(()) => {
+                self.persisted = true;
+                Ok(())
+            }
+            // Renaming across file systems fails, so we copy instead. The copy blocks,
+            // like the rename, so a dropped write can't leave it running in the background.
+            Err(err) if is_cross_device(&err) => std::fs::copy(&self.path, path).map(|_| ()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Get a file name with the given extension that no other file written by
+/// this process uses.
+pub fn unique_file_name(extension: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("bincache-{}-{n}.{extension}", std::process::id())
+}
+
+/// Check if an error was caused by renaming a file across file systems.
+fn is_cross_device(err: &std::io::Error) -> bool {
+    // `EXDEV` on Unix, `ERROR_NOT_SAME_DEVICE` on Windows
+    #[cfg(unix)]
+    const CROSS_DEVICE: Option<i32> = Some(18);
+    #[cfg(windows)]
+    const CROSS_DEVICE: Option<i32> = Some(17);
+    #[cfg(not(any(unix, windows)))]
+    const CROSS_DEVICE: Option<i32> = None;
+
+    CROSS_DEVICE.is_some() && err.raw_os_error() == CROSS_DEVICE
+}
+
+/// Get the length of a file in bytes.
+///
+/// Returns `None` if the file doesn't exist.
+pub async fn file_len(path: impl AsRef<Path>) -> Result<Option<u64>> {
+    #[cfg(any(
+        feature = "blocking",
+        all(
+            feature = "implicit-blocking",
+            not(any(feature = "rt_tokio_1", feature = "rt_async-std_1")),
+        )
+    ))]
+    let metadata = std::fs::metadata(path);
+    #[cfg(feature = "rt_tokio_1")]
+    let metadata = tokio::fs::metadata(path).await;
+    #[cfg(feature = "rt_async-std_1")]
+    let metadata = async_std::fs::metadata(path.as_ref()).await;
+
+    match metadata {
+        Ok(metadata) => Ok(Some(metadata.len())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
 pub async fn delete(path: impl AsRef<Path>) -> Result<()> {
     #[cfg(any(
         feature = "blocking",
@@ -118,3 +463,372 @@ pub async fn delete(path: impl AsRef<Path>) -> Result<()> {
         Ok(async_std::fs::remove_file(path.as_ref()).await?)
     }
 }
+
+/// Rename a file, replacing the target if it already exists.
+pub async fn rename(source: impl AsRef<Path>, target: impl AsRef<Path>) -> Result<()> {
+    #[cfg(any(
+        feature = "blocking",
+        all(
+            feature = "implicit-blocking",
+            not(any(feature = "rt_tokio_1", feature = "rt_async-std_1")),
+        )
+    ))]
+    {
+        Ok(std::fs::rename(source, target)?)
+    }
+    #[cfg(feature = "rt_tokio_1")]
+    {
+        Ok(tokio::fs::rename(source, target).await?)
+    }
+    #[cfg(feature = "rt_async-std_1")]
+    {
+        Ok(async_std::fs::rename(source.as_ref(), target.as_ref()).await?)
+    }
+}
+
+/// Delete a file, or move it into `archive_dir` if set.
+///
+/// A file that doesn't exist anymore counts as deleted.
+pub async fn delete_or_archive(path: impl AsRef<Path>, archive_dir: Option<&Path>) -> Result<()> {
+    let result = match (archive_dir, path.as_ref().file_name()) {
+        (Some(archive_dir), Some(file_name)) => rename(&path, archive_dir.join(file_name)).await,
+        _ => delete(path).await,
+    };
+    match result {
+        Err(crate::Error::IoError(err)) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Set the Unix permissions of a file or directory.
+///
+/// This is a no-op if `mode` is `None`, or on non-Unix platforms.
+pub async fn set_mode(path: impl AsRef<Path>, mode: Option<u32>) -> Result<()> {
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+        let permissions = Permissions::from_mode(mode);
+
+        #[cfg(any(
+            feature = "blocking",
+            all(
+                feature = "implicit-blocking",
+                not(any(feature = "rt_tokio_1", feature = "rt_async-std_1")),
+            )
+        ))]
+        std::fs::set_permissions(&path, permissions)?;
+        #[cfg(feature = "rt_tokio_1")]
+        tokio::fs::set_permissions(&path, permissions).await?;
+        #[cfg(feature = "rt_async-std_1")]
+        async_std::fs::set_permissions(path.as_ref(), permissions).await?;
+    }
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+
+    Ok(())
+}
+
+/// Recover entry files from a cache directory.
+/// Returns the recovered keys along with each file's path, value length and header.
+///
+/// Files whose name fails `should_consider` are left untouched, and files
+/// whose key can't be recovered are moved to the `lost+found` directory.
+pub async fn recover_dir<K, F, P>(
+    dir: &Path,
+    recover_key: F,
+    should_consider: P,
+) -> Result<Vec<(K, PathBuf, usize, Option<EntryHeader>)>>
+where
+    F: Fn(&str) -> Option<K>,
+    P: Fn(&str) -> bool,
+{
+    scan_dir(dir, recover_key, should_consider, true, None).await
+}
+
+/// Recover entry files from a cache directory, like [recover_dir], but skip
+/// files that can't be read instead of failing.
+/// Returns the recovered keys along with each file's path, value length and header, and a
+/// report counting the relocated and unreadable files.
+pub async fn recover_dir_with_report<K, F, P>(
+    dir: &Path,
+    recover_key: F,
+    should_consider: P,
+) -> Result<(
+    Vec<(K, PathBuf, usize, Option<EntryHeader>)>,
+    RecoveryReport,
+)>
+where
+    F: Fn(&str) -> Option<K>,
+    P: Fn(&str) -> bool,
+{
+    let mut report = RecoveryReport::default();
+    let files = scan_dir(dir, recover_key, should_consider, true, Some(&mut report)).await?;
+    Ok((files, report))
+}
+
+/// Recover entry files from a cache directory, like [recover_dir], but only read
+/// each file's header and take the value's length from the file's metadata.
+pub async fn recover_dir_fast<K, F, P>(
+    dir: &Path,
+    recover_key: F,
+    should_consider: P,
+) -> Result<Vec<(K, PathBuf, usize, Option<EntryHeader>)>>
+where
+    F: Fn(&str) -> Option<K>,
+    P: Fn(&str) -> bool,
+{
+    scan_dir(dir, recover_key, should_consider, false, None).await
+}
+
+/// Collect the entry files of a cache directory, reading them if `read_files` is set.
+///
+/// With a `report`, unreadable files are counted and skipped instead of failing.
+async fn scan_dir<K, F, P>(
+    dir: &Path,
+    recover_key: F,
+    should_consider: P,
+    read_files: bool,
+    mut report: Option<&mut RecoveryReport>,
+) -> Result<Vec<(K, PathBuf, usize, Option<EntryHeader>)>>
+where
+    F: Fn(&str) -> Option<K>,
+    P: Fn(&str) -> bool,
+{
+    // Create the `lost+found` directory
+    let lost_found_dir = dir.join("lost+found");
+    std::fs::create_dir_all(&lost_found_dir)?;
+
+    // Closure to move files to the `lost+found` directory
+    let move_to_lost_found = |source: &Path| {
+        // We explcitly ignore any errors here, as we don't want to fail
+        // the entire recovery process because of a single file.
+        let Some(file_name) = source.file_name() else {
+            return false;
+        };
+        let target_path = lost_found_dir.join(file_name);
+        std::fs::rename(source, target_path).is_ok()
+    };
+
+    // Iterate over all files in the directory
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+
+        // Skip directories
+        if path.is_dir() {
+            continue;
+        }
+
+        let file_name = path.file_name().and_then(|p| p.to_str());
+
+        // Skip files the caller isn't interested in
+        if file_name.map_or(false, |name| !should_consider(name)) {
+            continue;
+        }
+
+        // If key recovery fails, we move the entry to the `lost+found` directory.
+        let Some(key) = file_name.and_then(&recover_key) else {
+            if move_to_lost_found(&path) {
+                if let Some(report) = report.as_deref_mut() {
+                    report.relocated_to_lost_found += 1;
+                }
+            }
+            continue;
+        };
+
+        let (header, byte_len) = match read_files {
+            true => match (read(&path, None).await, report.as_deref_mut()) {
+                (Ok((header, buf)), _) => (header, buf.len()),
+                (Err(_), Some(report)) => {
+                    report.read_errors += 1;
+                    continue;
+                }
+                (Err(err), None) => return Err(err),
+            },
+            false => read_header(&path).await?,
+        };
+
+        files.push((key, path, byte_len, header));
+    }
+
+    Ok(files)
+}
+
+/// Remove files from the `lost+found` directory of a cache directory that
+/// haven't been modified within `max_age`.
+/// Returns the number of removed files.
+pub fn remove_stale_lost_found(dir: &Path, max_age: Duration) -> Result<usize> {
+    remove_stale_files(&dir.join("lost+found"), Some(max_age), |_| true)
+}
+
+/// Remove temporary files left behind by interrupted writes from the temp
+/// directory of a cache directory that haven't been modified within `max_age`.
+/// Returns the number of removed files.
+pub fn remove_stale_temp_files(dir: &Path, max_age: Duration) -> Result<usize> {
+    remove_stale_files(&dir.join(TEMP_DIR_NAME), Some(max_age), |file_name| {
+        file_name.starts_with("bincache-") && file_name.ends_with(".tmp")
+    })
+}
+
+/// Remove the spill files of other processes from a spill directory, see
+/// [Memory::with_spill_dir](crate::strategies::Memory::with_spill_dir).
+/// Returns the number of removed files.
+pub fn remove_leftover_spill_files(dir: &Path) -> Result<usize> {
+    let own_prefix = format!("bincache-{}-", std::process::id());
+    remove_stale_files(dir, None, |file_name| {
+        file_name.starts_with("bincache-")
+            && file_name.ends_with(".spill")
+            && !file_name.starts_with(&own_prefix)
+    })
+}
+
+/// Remove files from `dir` whose name matches `filter` and that haven't been
+/// modified within `max_age`, or regardless of their age without one.
+/// Returns the number of removed files.
+fn remove_stale_files<F>(dir: &Path, max_age: Option<Duration>, filter: F) -> Result<usize>
+where
+    F: Fn(&str) -> bool,
+{
+    if !dir.is_dir() {
+        return Ok(0);
+    }
+
+    let mut removed_file_count = 0;
+    for entry in std::fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() || !entry.file_name().to_str().map_or(false, &filter) {
+            continue;
+        }
+
+        // Files of unknown age are kept
+        let is_stale = max_age.map_or(true, |max_age| {
+            metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .map_or(false, |age| age > max_age)
+        });
+        if is_stale {
+            std::fs::remove_file(entry.path())?;
+            removed_file_count += 1;
+        }
+    }
+
+    Ok(removed_file_count)
+}
+
+/// Remove empty directories below a cache directory, keeping the cache
+/// directory itself along with the temp and `lost+found` directories.
+/// Returns the number of removed directories.
+pub fn remove_empty_dirs(dir: &Path) -> Result<usize> {
+    let mut removed_dir_count = 0;
+    for entry in std::fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+        let file_name = entry.file_name();
+        if file_name == TEMP_DIR_NAME || file_name == "lost+found" {
+            continue;
+        }
+        if !entry
+            .file_type()
+            .map_or(false, |file_type| file_type.is_dir())
+        {
+            continue;
+        }
+
+        removed_dir_count += remove_empty_dirs(&entry.path())?;
+        // Only succeeds if the directory is empty
+        if std::fs::remove_dir(entry.path()).is_ok() {
+            removed_dir_count += 1;
+        }
+    }
+    Ok(removed_dir_count)
+}
+
+/// Recover entry files from the `lost+found` directory of a cache directory.
+/// Returns the recovered keys along with each file's new path, value length and header.
+///
+/// Files whose key can be recovered are moved back into the cache directory.
+/// Files whose key still can't be recovered, or whose name is already taken
+/// in the cache directory, stay in `lost+found`.
+pub async fn recover_lost_found<K, F>(
+    dir: &Path,
+    recover_key: F,
+) -> Result<Vec<(K, PathBuf, usize, Option<EntryHeader>)>>
+where
+    F: Fn(&str) -> Option<K>,
+{
+    let lost_found_dir = dir.join("lost+found");
+    if !lost_found_dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    // Iterate over all files in the `lost+found` directory
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(&lost_found_dir)?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+
+        // Skip directories
+        if path.is_dir() {
+            continue;
+        }
+
+        let Some(file_name) = path.file_name().and_then(|p| p.to_str()) else {
+            continue;
+        };
+        let Some(key) = recover_key(file_name) else {
+            continue;
+        };
+
+        // Don't overwrite entries that already exist in the cache directory
+        let target_path = dir.join(file_name);
+        if target_path.exists() {
+            continue;
+        }
+
+        // We explicitly ignore errors moving a single file, as we don't want
+        // to fail the entire recovery process because of it.
+        if std::fs::rename(&path, &target_path).is_err() {
+            continue;
+        }
+
+        let (header, byte_len) = read_header(&target_path).await?;
+        files.push((key, target_path, byte_len, header));
+    }
+
+    Ok(files)
+}
+
+/// Get the number of bytes the files in a directory occupy on disk, including
+/// subdirectories.
+///
+/// On Unix, this is based on allocated blocks, so it includes file system overhead.
+/// Elsewhere, file lengths are used. The `lost+found` directory is only included
+/// if `include_lost_found` is set.
+pub fn dir_usage(dir: &Path, include_lost_found: bool) -> Result<u64> {
+    let mut usage = 0;
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)?.filter_map(|e| e.ok()) {
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                if include_lost_found || entry.file_name() != "lost+found" {
+                    dirs.push(entry.path());
+                }
+                continue;
+            }
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                usage += metadata.blocks() * 512;
+            }
+            #[cfg(not(unix))]
+            {
+                usage += metadata.len();
+            }
+        }
+    }
+    Ok(usage)
+}
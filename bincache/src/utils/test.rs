@@ -2,3 +2,5 @@ mod temp_dir;
 pub use temp_dir::TempDir;
 mod temp_arb_data;
 pub use temp_arb_data::create_arb_data;
+mod entry_file;
+pub use entry_file::read_entry_file;
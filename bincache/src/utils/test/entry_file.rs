@@ -0,0 +1,12 @@
+use std::path::Path;
+
+use crate::entry_header::EntryHeader;
+
+/// Read an entry file, skipping the header in front of the value if there is one.
+pub fn read_entry_file(path: impl AsRef<Path>) -> Vec<u8> {
+    let mut data = std::fs::read(path).unwrap();
+    if let Ok((_, header_len)) = EntryHeader::decode(&data) {
+        data.drain(..header_len);
+    }
+    data
+}